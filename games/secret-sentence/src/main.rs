@@ -1,8 +1,10 @@
 
-use std::time::Instant;
-
-use genetic_rs::criterion::Mark;
+use genetic_rs::criterion::*;
 use genetic_rs::selector::*;
+use genetic_rs::mating::Random;
+use genetic_rs::genome::list::{CutAndSplice, RandomList};
+use genetic_rs::memetic::{Inheritance, Memetic, NoLocalSearch, Target};
+use genetic_rs::result::{Archives, HallOfFame};
 
 mod my_crossover;
 mod my_evaluator;
@@ -16,22 +18,54 @@ use my_mutation::*;
 
 
 fn main() {
+    if std::env::args().any(|a| a == "--variable") {
+        variable_length();
+        return;
+    }
+
     //let selector = Rating{ max_pop: 200 };
     //let selector = Elitism{ max_pop: 200 };
     //let selector = Rank{ max_pop: 200 };
     let selector = Tournament{ max_pop: 100 };
     let evaluator = BasicEvaluation{ solution: String::from("coucoualexjtmbb") };
     let generator = BasicGenerator{ string_size: evaluator.solution.len() };
-    let stop_crit = Mark{ max_rating: evaluator.solution.len() as f32 };
+    let mut stop_crit = Mark{ max_rating: evaluator.solution.len() as f32 };
+    let mating = Random;
     let crossover = BasicCrossover;
     let mutation = BasicMutation;
     let pop_size = 1000;
 
-    let instant = Instant::now();
-
-    let (solution, gen) = genetic_rs::generate(&generator, &evaluator, &selector,
-        &crossover, &mutation, &stop_crit, pop_size);
+    let result = genetic_rs::generate(&generator, &evaluator, &selector, &mating,
+        &crossover, &mutation, &mut stop_crit, pop_size);
         
-    let time = instant.elapsed().as_millis();
-    println!("Found solution: {solution} ; in {gen} generations and in {time}ms");
+    let time = result.elapsed.as_millis();
+    println!("Found solution: {} ; in {} generations and in {time}ms", result.best, result.generations);
+}
+
+// The length of the sentence is evolved too, candidates start between 1 and
+// twice the length of the solution
+fn variable_length() {
+    let selector = Tournament{ max_pop: 100 };
+    let evaluator = LevensteinEvaluation{ solution: String::from("coucoualexjtmbb") };
+    let max_len = evaluator.solution.len() * 2;
+    let generator = RandomList{ min_len: 1, max_len, gene: AlphanumericGene };
+    let mut stop_crit = Mark{ max_rating: evaluator.solution.len() as f32 }.or(Iterations::new(5000));
+    let mating = Random;
+    let crossover = CutAndSplice{ min_len: 1, max_len };
+    let mutation = LengthMutation{ max_len };
+    let pop_size = 1000;
+    let memetic = Memetic{ local_search: NoLocalSearch, probability: 0.0, budget: 0, target: Target::Offspring,
+        inheritance: Inheritance::Lamarckian };
+    // Distinct sentences only, for the runner-ups
+    let archives = Archives{ hall_of_fame: HallOfFame::unique(4), pareto: None, diversity: None };
+
+    let result = genetic_rs::generate_archived(&generator, &evaluator, &selector, &mating,
+        &crossover, &mutation, &memetic, archives, &mut stop_crit, pop_size);
+
+    let time = result.elapsed.as_millis();
+    let solution: String = result.best.into_iter().collect();
+    println!("Found solution: {solution} ; in {} generations and in {time}ms", result.generations);
+    for (candidate, rating) in result.hall_of_fame.entries().iter().skip(1).take(3) {
+        println!("Runner-up: {} ({rating})", candidate.iter().collect::<String>());
+    }
 }
@@ -99,3 +99,13 @@ impl Evaluator<String> for LevensteinEvaluation {
         (self.solution.len() - self.levenshtein_distance(&self.solution, pop)) as f32
     }
 }
+
+// Candidates of any length, the distance also counts missing or extra letters
+impl Evaluator<Vec<char>> for LevensteinEvaluation {
+
+    fn evaluator(&self, pop: &Vec<char>) -> f32 {
+        let candidate: String = pop.iter().collect();
+
+        self.solution.len() as f32 - self.levenshtein_distance(&self.solution, &candidate) as f32
+    }
+}
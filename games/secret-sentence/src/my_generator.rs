@@ -15,4 +15,13 @@ impl Generator<String> for BasicGenerator {
         .map(char::from)
         .collect()
     }
-}
\ No newline at end of file
+}
+
+pub struct AlphanumericGene;
+
+impl Generator<char> for AlphanumericGene {
+
+    fn generator(&self) -> char {
+        thread_rng().sample(Alphanumeric) as char
+    }
+}
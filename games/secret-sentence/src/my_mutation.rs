@@ -1,6 +1,9 @@
 use rand::{thread_rng, Rng, distributions::Alphanumeric};
 
 use genetic_rs::mutation::Mutation;
+use genetic_rs::genome::list::{Deletion, Insertion, Replace};
+
+use crate::my_generator::AlphanumericGene;
 
 pub struct BasicMutation;
 
@@ -18,4 +21,20 @@ impl Mutation<String> for BasicMutation
         }
         *pop = new_string;
     }
-}
\ No newline at end of file
+}
+
+// Changes either the letters or the length of the sentence
+pub struct LengthMutation {
+    pub max_len: usize
+}
+
+impl Mutation<Vec<char>> for LengthMutation
+{
+    fn mutation(&self, pop: &mut Vec<char>) {
+        match thread_rng().gen_range(0..3) {
+            0 => Insertion{ max_len: self.max_len, gene: AlphanumericGene }.mutation(pop),
+            1 => Deletion{ min_len: 1 }.mutation(pop),
+            _ => Replace{ rate: 0.1, gene: AlphanumericGene }.mutation(pop)
+        }
+    }
+}
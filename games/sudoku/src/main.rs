@@ -1,7 +1,10 @@
-use std::time::Instant;
-
 use genetic_rs::criterion::*;
 use genetic_rs::selector::*;
+use genetic_rs::mating::*;
+use genetic_rs::crossover::OneOf as OneOfCrossover;
+use genetic_rs::mutation::Mutation;
+use genetic_rs::adaptive::{AdaptiveMutation, Strategy};
+use genetic_rs::memetic::{Memetic, FirstImprovement, Target, Inheritance};
 
 mod sudoku;
 mod my_crossover;
@@ -51,21 +54,40 @@ fn main() {
     //let selector = Rank{ max_pop: 2000 };
     //let selector = Tournament{ max_pop: 200 };
     let selector = BestAndRand{ best_pop: 1500, rand_pop: 1500 };
+    let mating = Random;
+    //let mating = IncestPrevention{ threshold: 4, attempts: 10 };
     let evaluator = BasicEvaluation;
     let generator = BasicGenerator{ sudoku };
-    let mut stop_crit = Mark{ max_rating: u8::MAX as f32 };
-    //let mut stop_crit = Plateau::new(1000);
-    let crossover = BasicCrossover;
-    //let crossover = HalfCrossover;
-    let mutation = BasicMutation;
-    //let mutation = SingleMutation;
+    let mut stop_crit = Mark{ max_rating: u8::MAX as f32 }
+        .or(Plateau::new(1000))
+        .or(Iterations::new(50000));
+    let crossover = OneOfCrossover::new(vec![
+        (3.0, Box::new(BasicCrossover)),
+        (1.0, Box::new(HalfCrossover))
+    ]);
+    let mutation = AdaptiveMutation::new(vec![
+        Box::new(BasicMutation),
+        Box::new(SingleMutation)
+    ], Strategy::AdaptivePursuit { p_min: 0.1, alpha: 0.3, beta: 0.1 });
+    // A few steps of hill climbing on swaps for some of the children
+    let memetic = Memetic {
+        local_search: FirstImprovement{ neighbourhood: swaps },
+        probability: 0.05,
+        budget: 50,
+        target: Target::Offspring,
+        inheritance: Inheritance::Lamarckian
+    };
     let pop_size = 5000;
 
-    let instant = Instant::now();
-
-    let (solution, gen, score) = genetic_rs::generate(&generator, &evaluator, &selector,
-        &crossover, &mutation, &mut stop_crit, pop_size);
+    let result = genetic_rs::generate_memetic(&generator, &evaluator, &selector, &mating,
+        &crossover, &mutation, &memetic, &mut stop_crit, pop_size);
         
-    let time = instant.elapsed().as_millis();
-    println!("Found solution: {solution} ; in {gen} generations and in {time}ms with score of {score}");
+    let time = result.elapsed.as_millis();
+    println!("Found solution: {} ; in {} generations and in {time}ms with score of {}", result.best,
+        result.generations, result.rating);
+    println!("Stopped because: {} after {} evaluations", result.reason, result.evaluations);
+    for stats in mutation.stats() {
+        println!("{}: used {} times, {:.1}% success, p = {:.2}", stats.name, stats.uses,
+            stats.success_rate() * 100.0, stats.probability);
+    }
 }
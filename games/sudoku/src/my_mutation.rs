@@ -38,4 +38,22 @@ impl Mutation<Sudoku> for SingleMutation
 
         mutating_cell.data.swap(to_permute.pop().unwrap(), to_permute.pop().unwrap());
     }
-}
\ No newline at end of file
+}
+
+// Every grid one swap of mutable values away, within a cell
+pub fn swaps(pop: &Sudoku) -> Vec<Sudoku> {
+    let mut neighbours = vec![];
+
+    for (c, cell) in pop.cells.iter().enumerate() {
+        let mutables: Vec<_> = (0..9).filter(|x| !cell.immutables.contains(x)).collect();
+        for (i, a) in mutables.iter().enumerate() {
+            for b in &mutables[i + 1..] {
+                let mut neighbour = pop.clone();
+                neighbour.cells[c].data.swap(*a, *b);
+                neighbours.push(neighbour);
+            }
+        }
+    }
+
+    neighbours
+}
@@ -0,0 +1,275 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::thread::ThreadId;
+
+use rand::{thread_rng, distributions::WeightedIndex, prelude::Distribution};
+
+use crate::crossover::Crossover;
+use crate::mutation::Mutation;
+use crate::state::OperatorStats;
+
+// How the operator to apply is picked from the rewards observed so far. The
+// reward of an application is the improvement of the child on its parents,
+// 0 if it got worse.
+#[derive(Clone, Copy, Debug)]
+pub enum Strategy {
+    // Chances proportional to the estimated rewards, never below `p_min`.
+    // `alpha` is the adaptation rate of the estimates.
+    ProbabilityMatching { p_min: f64, alpha: f64 },
+    // Chances of the best operator pushed towards 1 - (K - 1) * p_min, the
+    // others towards p_min, at the `beta` rate
+    AdaptivePursuit { p_min: f64, alpha: f64, beta: f64 },
+    // Upper confidence bound bandit, `c` weights the exploration
+    Ucb { c: f64 }
+}
+
+struct Adaptation {
+    strategy: Strategy,
+    stats: Vec<OperatorStats>,
+    // Operators applied and not credited yet, in call order, for each thread.
+    // Runners credit on the thread that applied the operators, so the
+    // parallel local searches of `memetic` don't mix up their credits.
+    pending: HashMap<ThreadId, VecDeque<usize>>
+}
+
+impl Adaptation {
+
+    fn new(strategy: Strategy, names: Vec<String>) -> Self {
+        let operators = names.len();
+        if let Strategy::ProbabilityMatching { p_min, .. } | Strategy::AdaptivePursuit { p_min, .. } = strategy {
+            assert!(p_min * operators as f64 <= 1.0, "p_min is above 1 / {operators}, the chances can't add up");
+        }
+
+        let probability = 1.0 / operators.max(1) as f64;
+
+        Self {
+            strategy,
+            stats: names.into_iter().map(|name| OperatorStats{ name, probability, ..Default::default() }).collect(),
+            pending: HashMap::new()
+        }
+    }
+
+    fn pick(&mut self) -> usize {
+        let i = match self.strategy {
+            Strategy::Ucb { c } => {
+                let total: usize = self.stats.iter().map(|s| s.uses).sum();
+                let best = self.stats.iter().map(|s| s.quality).fold(0.0, f64::max);
+                let bound = |s: &OperatorStats| {
+                    if s.uses == 0 {
+                        return f64::INFINITY;
+                    }
+                    // Qualities are scaled so that `c` does not depend on
+                    // the magnitude of the ratings
+                    let quality = if best > 0.0 { s.quality / best } else { 0.0 };
+                    quality + c * (2.0 * (total as f64).ln() / s.uses as f64).sqrt()
+                };
+
+                (0..self.stats.len())
+                    .max_by(|a, b| bound(&self.stats[*a]).total_cmp(&bound(&self.stats[*b])))
+                    .unwrap()
+            },
+            _ => {
+                let dist = WeightedIndex::new(self.stats.iter().map(|s| s.probability)).unwrap();
+                dist.sample(&mut thread_rng())
+            }
+        };
+        self.pending.entry(std::thread::current().id()).or_default().push_back(i);
+
+        i
+    }
+
+    fn credit(&mut self, improvements: &[f32]) {
+        // Applications the runner did not report are dropped with the queue
+        let Some(pending) = self.pending.remove(&std::thread::current().id()) else {
+            return;
+        };
+        for (i, improvement) in pending.into_iter().zip(improvements) {
+            self.reward(i, *improvement as f64);
+        }
+    }
+
+    fn reward(&mut self, i: usize, improvement: f64) {
+        let reward = improvement.max(0.0);
+        let stats = &mut self.stats[i];
+        stats.uses += 1;
+        if improvement > 0.0 {
+            stats.successes += 1;
+        }
+
+        let k = self.stats.len() as f64;
+        match self.strategy {
+            Strategy::ProbabilityMatching { p_min, alpha } => {
+                self.stats[i].quality += alpha * (reward - self.stats[i].quality);
+
+                let total: f64 = self.stats.iter().map(|s| s.quality).sum();
+                for s in self.stats.iter_mut() {
+                    s.probability = if total > 0.0 {
+                        p_min + (1.0 - k * p_min) * s.quality / total
+                    } else {
+                        1.0 / k
+                    };
+                }
+            },
+            Strategy::AdaptivePursuit { p_min, alpha, beta } => {
+                self.stats[i].quality += alpha * (reward - self.stats[i].quality);
+
+                let best = (0..self.stats.len())
+                    .max_by(|a, b| self.stats[*a].quality.total_cmp(&self.stats[*b].quality))
+                    .unwrap();
+                let p_max = 1.0 - (k - 1.0) * p_min;
+                for (j, s) in self.stats.iter_mut().enumerate() {
+                    let target = if j == best { p_max } else { p_min };
+                    s.probability += beta * (target - s.probability);
+                }
+            },
+            Strategy::Ucb { .. } => {
+                let stats = &mut self.stats[i];
+                stats.quality += (reward - stats.quality) / stats.uses as f64;
+
+                let total: usize = self.stats.iter().map(|s| s.uses).sum();
+                for s in self.stats.iter_mut() {
+                    s.probability = s.uses as f64 / total as f64;
+                }
+            }
+        }
+    }
+}
+
+// Learns which of its mutations work best during the run. Must be the
+// mutation handed to the engine, wrapping it in another combinator hides the
+// credit from it.
+pub struct AdaptiveMutation<T> {
+    pub operators: Vec<Box<dyn Mutation<T>>>,
+    adaptation: Mutex<Adaptation>
+}
+
+impl<T> AdaptiveMutation<T> {
+
+    pub fn new(operators: Vec<Box<dyn Mutation<T>>>, strategy: Strategy) -> Self {
+        let adaptation = Mutex::new(Adaptation::new(strategy, operators.iter().map(|m| m.name()).collect()));
+
+        Self {
+            operators,
+            adaptation
+        }
+    }
+}
+
+impl<T> Mutation<T> for AdaptiveMutation<T>
+{
+    fn mutation(&self, pop: &mut T) {
+        let i = self.adaptation.lock().unwrap().pick();
+
+        self.operators[i].mutation(pop);
+    }
+
+    fn credit(&self, improvements: &[f32]) {
+        self.adaptation.lock().unwrap().credit(improvements);
+    }
+
+    fn stats(&self) -> Vec<OperatorStats> {
+        self.adaptation.lock().unwrap().stats.clone()
+    }
+}
+
+// Learns which of its crossovers work best during the run
+pub struct AdaptiveCrossover<T> {
+    pub operators: Vec<Box<dyn Crossover<T>>>,
+    adaptation: Mutex<Adaptation>
+}
+
+impl<T> AdaptiveCrossover<T> {
+
+    pub fn new(operators: Vec<Box<dyn Crossover<T>>>, strategy: Strategy) -> Self {
+        let adaptation = Mutex::new(Adaptation::new(strategy, operators.iter().map(|c| c.name()).collect()));
+
+        Self {
+            operators,
+            adaptation
+        }
+    }
+}
+
+impl<T> Crossover<T> for AdaptiveCrossover<T>
+{
+    fn crossover(&self, parent1: &T, parent2: &T) -> T {
+        let i = self.adaptation.lock().unwrap().pick();
+
+        self.operators[i].crossover(parent1, parent2)
+    }
+
+    fn children(&self) -> usize {
+        self.operators.iter().map(|c| c.children()).max().unwrap_or(1)
+    }
+
+    fn crossover_pair(&self, parent1: &T, parent2: &T) -> (T, T) {
+        let i = self.adaptation.lock().unwrap().pick();
+
+        self.operators[i].crossover_pair(parent1, parent2)
+    }
+
+    fn credit(&self, improvements: &[f32]) {
+        self.adaptation.lock().unwrap().credit(improvements);
+    }
+
+    fn stats(&self) -> Vec<OperatorStats> {
+        self.adaptation.lock().unwrap().stats.clone()
+    }
+}
+
+#[cfg(test)]
+fn run_bandit(strategy: Strategy) -> Vec<OperatorStats> {
+    struct Nop;
+
+    impl Mutation<i32> for Nop
+    {
+        fn mutation(&self, _pop: &mut i32) {}
+    }
+
+    struct Good;
+
+    impl Mutation<i32> for Good
+    {
+        fn mutation(&self, pop: &mut i32) {
+            *pop += 1;
+        }
+    }
+
+    let mutation = AdaptiveMutation::new(vec![Box::new(Nop), Box::new(Good)], strategy);
+    for _ in 0..200 {
+        let mut improvements = vec![];
+        for _ in 0..10 {
+            let mut pop = 0;
+            mutation.mutation(&mut pop);
+            improvements.push(pop as f32);
+        }
+        mutation.credit(&improvements);
+    }
+
+    let stats = mutation.stats();
+    assert_eq!((stats[0].name.as_str(), stats[1].name.as_str()), ("Nop", "Good"));
+
+    stats
+}
+
+#[test]
+fn test_strategies_favor_best_operator() {
+    let strategies = [
+        Strategy::ProbabilityMatching { p_min: 0.05, alpha: 0.3 },
+        Strategy::AdaptivePursuit { p_min: 0.05, alpha: 0.3, beta: 0.3 },
+        Strategy::Ucb { c: 0.5 }
+    ];
+
+    for strategy in strategies {
+        let stats = run_bandit(strategy);
+        assert!(stats[1].probability > 0.8, "{strategy:?}: {stats:?}");
+        assert_eq!(stats[0].successes, 0);
+        assert_eq!(stats[1].success_rate(), 1.0);
+    }
+}
+
+#[test]
+#[should_panic(expected = "p_min")]
+fn test_p_min_too_high() {
+    run_bandit(Strategy::ProbabilityMatching { p_min: 0.6, alpha: 0.3 });
+}
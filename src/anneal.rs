@@ -0,0 +1,104 @@
+use rand::{thread_rng, Rng};
+
+use crate::criterion::Criterion;
+use crate::evaluator::Evaluator;
+use crate::generator::Generator;
+use crate::mutation::Mutation;
+use crate::result::RunResult;
+use crate::state::RunState;
+
+// Temperature of each step, a step being one move tried
+#[derive(Clone, Copy, Debug)]
+pub enum Cooling {
+    Constant(f64),
+    // From start down to 0 over `steps`
+    Linear { start: f64, steps: usize },
+    // start * alpha^step
+    Geometric { start: f64, alpha: f64 },
+    // start / ln(step + e), slow but with convergence guarantees
+    Logarithmic { start: f64 }
+}
+
+impl Cooling {
+
+    pub fn temperature(&self, step: usize) -> f64 {
+        match *self {
+            Cooling::Constant(temperature) => temperature,
+            Cooling::Linear { start, steps } => start * (1.0 - step as f64 / steps.max(1) as f64).max(0.0),
+            Cooling::Geometric { start, alpha } => start * alpha.powf(step as f64),
+            Cooling::Logarithmic { start } => start / (step as f64 + std::f64::consts::E).ln()
+        }
+    }
+}
+
+// Metropolis rule: better moves are always taken, worse ones with
+// probability exp(delta / temperature)
+pub(crate) fn accept<R: Rng>(rng: &mut R, delta: f64, temperature: f64) -> bool {
+    delta >= 0.0 || (temperature > 0.0 && rng.gen::<f64>() < (delta / temperature).exp())
+}
+
+// Simulated annealing from a generated individual, `mutation` making the
+// moves. Better moves are always taken, worse ones with probability
+// exp(delta / temperature). Each step is a generation of a single rating for
+// the criterion. Returns the best individual met.
+pub fn anneal<T, G, E, M, F>(generator: &G, evaluator: &E, mutation: &M, stop_crit: &mut F,
+    cooling: Cooling) -> RunResult<T>
+where
+    G: Generator<T>,
+    E: Evaluator<T>,
+    M: Mutation<T>,
+    F: Criterion,
+    T: Clone {
+
+    let mut rng = thread_rng();
+
+    let mut state = RunState::new();
+
+    let mut current = generator.generator();
+    let mut current_rating = evaluator.evaluator(&current);
+    let (mut best, mut best_rating) = (current.clone(), current_rating);
+
+    state.record(&[current_rating], 1);
+
+    while !stop_crit.criterion(&state) {
+        let mut candidate = current.clone();
+        mutation.mutation(&mut candidate);
+        let rating = evaluator.evaluator(&candidate);
+        mutation.credit(&[rating - current_rating]);
+        state.mutation_stats = mutation.stats();
+
+        let delta = (rating - current_rating) as f64;
+        if accept(&mut rng, delta, cooling.temperature(state.generation)) {
+            current = candidate;
+            current_rating = rating;
+            if current_rating > best_rating {
+                best.clone_from(&current);
+                best_rating = current_rating;
+            }
+        }
+        state.record(&[current_rating], 1);
+    }
+
+    RunResult::new(best, best_rating, vec![current], vec![current_rating], state, stop_crit.reason())
+}
+
+#[test]
+fn test_cooling() {
+    assert_eq!(Cooling::Linear{ start: 10.0, steps: 10 }.temperature(5), 5.0);
+    assert_eq!(Cooling::Linear{ start: 10.0, steps: 10 }.temperature(20), 0.0);
+    assert_eq!(Cooling::Geometric{ start: 8.0, alpha: 0.5 }.temperature(3), 1.0);
+    assert!((Cooling::Logarithmic{ start: 2.0 }.temperature(0) - 2.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_anneal() {
+    use crate::criterion::{Iterations, Mark};
+    use crate::genome::bits::{BitFlip, OneMax, RandomBits};
+
+    let mut stop_crit = Mark{ max_rating: 32.0 }.or(Iterations::new(20000));
+    let result = anneal(&RandomBits{ len: 32 }, &OneMax, &BitFlip{ rate: 1.0 / 32.0 }, &mut stop_crit,
+        Cooling::Geometric{ start: 2.0, alpha: 0.999 });
+
+    assert_eq!(result.rating, 32.0);
+    assert_eq!(result.best.count_ones(), 32);
+}
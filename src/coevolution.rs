@@ -0,0 +1,425 @@
+use rand::{thread_rng, seq::SliceRandom};
+
+use crate::criterion::Criterion;
+use crate::crossover::MultiCrossover;
+use crate::generator::Generator;
+use crate::mating::Mating;
+use crate::mutation::Mutation;
+use crate::selector::Selector;
+use crate::result::{HallOfFame, RunResult};
+use crate::state::RunState;
+use crate::{best_index, breed, credit, Crossed, NUM_THREADS};
+
+// Game between two individuals, the score of each. Scores are added up over
+// the games an individual plays.
+pub trait Contest<T> {
+    fn contest(&self, first: &T, second: &T) -> (f32, f32);
+}
+
+// Rating of a whole team, one member of each population in population order
+pub trait Collaboration<T> {
+    fn collaboration(&self, team: &[&T]) -> f32;
+}
+
+// Who each individual plays against. Opponents come from the other
+// populations, or from its own population if there is only one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pairing {
+    // Every possible opponent, the rating is the mean score
+    RoundRobin,
+    // `opponents` random opponents, the rating is the mean score
+    Random { opponents: usize },
+    // Single elimination among all the individuals, the rating is the
+    // number of games won. Those left without an opponent go to the next
+    // round without winning anything, it ends once no game can be made.
+    Tournament
+}
+
+// `f(i)` for i in 0..len, spread over threads
+fn par_map<R, F>(len: usize, f: F) -> Vec<R>
+where
+    R: Send,
+    F: Fn(usize) -> R + Sync {
+
+    if len == 0 {
+        return vec![];
+    }
+
+    let chunk = len.div_ceil(NUM_THREADS as usize);
+    std::thread::scope(|scope| {
+        let f = &f;
+        let threads: Vec<_> = (0..len).step_by(chunk)
+            .map(|begin| scope.spawn(move || (begin..(begin + chunk).min(len)).map(f).collect::<Vec<_>>()))
+            .collect();
+
+        threads.into_iter().flat_map(|t| t.join().expect("Thread did not close correctly")).collect()
+    })
+}
+
+// Ratings of every population, and the number of games played
+fn play<T, C>(contest: &C, pops: &[Vec<T>], pairing: Pairing, hall: &[(T, f32)], hall_games: usize) -> (Vec<Vec<f32>>, usize)
+where
+    C: Contest<T> + Sync,
+    T: Sync {
+
+    // Individuals as (population, index)
+    let everyone: Vec<(usize, usize)> = (0..pops.len()).flat_map(|p| (0..pops[p].len()).map(move |i| (p, i))).collect();
+    let opponents = |p: usize, i: usize| -> Vec<&T> {
+        everyone.iter()
+            .filter(|(q, j)| if pops.len() == 1 { *j != i } else { *q != p })
+            .map(|(q, j)| &pops[*q][*j])
+            .collect()
+    };
+
+    let mut ratings: Vec<Vec<f32>> = pops.iter().map(|pop| vec![0.0; pop.len()]).collect();
+    let mut games = 0;
+
+    match pairing {
+        Pairing::RoundRobin | Pairing::Random { .. } => {
+            let scores = par_map(everyone.len(), |n| {
+                let (p, i) = everyone[n];
+                let mut pool = opponents(p, i);
+                if let Pairing::Random { opponents } = pairing {
+                    pool = pool.choose_multiple(&mut thread_rng(), opponents).copied().collect();
+                }
+                let total: f32 = pool.iter().map(|o| contest.contest(&pops[p][i], o).0).sum();
+                (total / pool.len().max(1) as f32, pool.len())
+            });
+
+            for ((p, i), (score, played)) in everyone.iter().zip(scores) {
+                ratings[*p][*i] = score;
+                games += played;
+            }
+        },
+        Pairing::Tournament => {
+            let mut round = everyone.clone();
+            round.shuffle(&mut thread_rng());
+
+            loop {
+                // Opponents from another population, if there are several
+                let (mut games_left, mut byes) = (vec![], vec![]);
+                while let Some((p, i)) = round.pop() {
+                    match round.iter().rposition(|(q, _)| pops.len() == 1 || *q != p) {
+                        Some(k) => games_left.push(((p, i), round.remove(k))),
+                        None => byes.push((p, i))
+                    }
+                }
+                if games_left.is_empty() {
+                    break;
+                }
+
+                let winners = par_map(games_left.len(), |n| {
+                    let ((p, i), (q, j)) = games_left[n];
+                    let (a, b) = contest.contest(&pops[p][i], &pops[q][j]);
+                    if a >= b { (p, i) } else { (q, j) }
+                });
+                games += winners.len();
+                for (p, i) in &winners {
+                    ratings[*p][*i] += 1.0;
+                }
+
+                round = winners;
+                round.extend(byes);
+                round.shuffle(&mut thread_rng());
+            }
+        }
+    }
+
+    // Games against past champions, keeping the populations from forgetting
+    // how to beat them
+    if !hall.is_empty() && hall_games > 0 {
+        let scores = par_map(everyone.len(), |n| {
+            let (p, i) = everyone[n];
+            let mut rng = thread_rng();
+            (0..hall_games).map(|_| contest.contest(&pops[p][i], &hall.choose(&mut rng).unwrap().0).0).sum::<f32>()
+                / hall_games as f32
+        });
+        for ((p, i), score) in everyone.iter().zip(scores) {
+            ratings[*p][*i] += score;
+        }
+        games += everyone.len() * hall_games;
+    }
+
+    (ratings, games)
+}
+
+// Next generation of every population. What was crossed and mutated is
+// indexed into the populations laid end to end, to credit the operators with
+// all the ratings of the next generation at once.
+#[allow(clippy::too_many_arguments)]
+fn breed_all<T, S, P, C, M>(pops: &[Vec<T>], ratings: &[Vec<f32>], selector: &S, mating: &P, crossover: &C,
+    mutation: &M, pop_size: usize) -> (Vec<Vec<T>>, Crossed, Vec<(f32, usize)>)
+where
+    S: Selector<T>,
+    P: Mating<T>,
+    C: MultiCrossover<T>,
+    M: Mutation<T> {
+
+    let (mut next, mut crossed, mut mutated) = (vec![], vec![], vec![]);
+    for (p, (pop, ratings)) in pops.iter().zip(ratings).enumerate() {
+        let (pop, c, m) = breed(pop, ratings, selector, mating, crossover, mutation, pop_size);
+        let offset = p * pop_size;
+        crossed.extend(c.into_iter().map(|(parent, children)| (parent, children.start + offset..children.end + offset)));
+        mutated.extend(m.into_iter().map(|(parent, child)| (parent, child + offset)));
+        next.push(pop);
+    }
+
+    (next, crossed, mutated)
+}
+
+// Competitive coevolution of `populations` populations of `pop_size`, each
+// rated from its games against the others (or among itself if there is only
+// one) and bred as in `generate`. With `hall_games` above 0 the champion of
+// every population is offered each generation to a hall of fame keeping the
+// `hall_size` best rated ones, and everyone plays that many games against
+// random members of it on top of `pairing`. Returns the champions of the last
+// generation, and the best of their ratings. Ratings are relative to the opponents of the generation, the
+// criterion sees those of all populations. The population of the result holds
+// the last populations, its ratings those of all their members in order.
+#[allow(clippy::too_many_arguments)]
+pub fn competitive<T, G, K, S, P, C, M, F>(generator: &G, contest: &K, selector: &S, mating: &P, crossover: &C,
+    mutation: &M, stop_crit: &mut F, pairing: Pairing, hall_games: usize, hall_size: usize,
+    populations: usize, pop_size: u32) -> RunResult<Vec<T>>
+where
+    G: Generator<T>,
+    K: Contest<T> + Sync,
+    S: Selector<T>,
+    P: Mating<T>,
+    C: MultiCrossover<T>,
+    M: Mutation<T>,
+    F: Criterion,
+    T: Clone + Send + Sync {
+
+    assert!(pop_size > 0, "competitive coevolution needs at least one individual per population");
+
+    let mut pops: Vec<Vec<T>> = (0..populations.max(1))
+        .map(|_| (0..pop_size).map(|_| generator.generator()).collect())
+        .collect();
+    let mut hall = HallOfFame::new(hall_size);
+    let mut state = RunState::new();
+    let (mut crossed, mut mutated) = (vec![], vec![]);
+
+    loop {
+        let (ratings, games) = play(contest, &pops, pairing, hall.entries(), hall_games);
+        credit(crossover, mutation, crossed, &mutated, &ratings.concat());
+        state.crossover_stats = crossover.stats();
+        state.mutation_stats = mutation.stats();
+        state.record(&ratings.concat(), games);
+
+        let champions: Vec<usize> = ratings.iter().map(|r| best_index(r).0).collect();
+        if hall_games > 0 {
+            for ((c, pop), r) in champions.iter().zip(&pops).zip(&ratings) {
+                hall.update(std::slice::from_ref(&pop[*c]), &r[*c..=*c]);
+            }
+        }
+
+        if stop_crit.criterion(&state) {
+            let best = ratings.iter().zip(&champions).map(|(r, c)| r[*c]).fold(f32::MIN, f32::max);
+            let champions = champions.iter().zip(&pops).map(|(c, pop)| pop[*c].clone()).collect();
+            return RunResult::new(champions, best, pops, ratings.concat(), state, stop_crit.reason());
+        }
+
+        (pops, crossed, mutated) = breed_all(&pops, &ratings, selector, mating, crossover, mutation, pop_size as usize);
+    }
+}
+
+// Cooperative coevolution (Potter and De Jong): population i evolves the
+// i-th member of a team. Each individual is rated with the best rated team
+// it makes with the best members of the other populations of the previous
+// generation, or with `collaborators` teams of random current members.
+// Returns the best team met and its rating, with the last populations as in
+// `competitive`.
+#[allow(clippy::too_many_arguments)]
+pub fn cooperative<T, G, K, S, P, C, M, F>(generator: &G, collaboration: &K, selector: &S, mating: &P,
+    crossover: &C, mutation: &M, stop_crit: &mut F, collaborators: usize, populations: usize, pop_size: u32)
+    -> RunResult<Vec<T>>
+where
+    G: Generator<T>,
+    K: Collaboration<T> + Sync,
+    S: Selector<T>,
+    P: Mating<T>,
+    C: MultiCrossover<T>,
+    M: Mutation<T>,
+    F: Criterion,
+    T: Clone + Send + Sync {
+
+    assert!(pop_size > 0, "cooperative coevolution needs at least one individual per population");
+
+    let populations = populations.max(1);
+    let mut pops: Vec<Vec<T>> = (0..populations)
+        .map(|_| (0..pop_size).map(|_| generator.generator()).collect())
+        .collect();
+    // Random members before the first ratings
+    let mut representatives: Vec<T> = pops.iter().map(|pop| pop.choose(&mut thread_rng()).unwrap().clone()).collect();
+
+    let mut state = RunState::new();
+    let (mut best_team, mut best_rating) = (vec![], f32::MIN);
+    let (mut crossed, mut mutated) = (vec![], vec![]);
+
+    loop {
+        let everyone: Vec<(usize, usize)> = (0..populations).flat_map(|p| (0..pop_size as usize).map(move |i| (p, i))).collect();
+        let rated = par_map(everyone.len(), |n| {
+            let (p, i) = everyone[n];
+            let mut rng = thread_rng();
+
+            let mut teams: Vec<Vec<&T>> = vec![representatives.iter().collect()];
+            for _ in 0..collaborators {
+                teams.push(pops.iter().map(|pop| pop.choose(&mut rng).unwrap()).collect());
+            }
+
+            teams.into_iter()
+                .map(|mut team| {
+                    team[p] = &pops[p][i];
+                    (collaboration.collaboration(&team), team)
+                })
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .unwrap()
+        });
+
+        let mut ratings: Vec<Vec<f32>> = vec![vec![0.0; pop_size as usize]; populations];
+        for ((p, i), (rating, team)) in everyone.iter().zip(&rated) {
+            ratings[*p][*i] = *rating;
+            if *rating > best_rating {
+                best_team = team.iter().map(|m| (*m).clone()).collect();
+                best_rating = *rating;
+            }
+        }
+        credit(crossover, mutation, crossed, &mutated, &ratings.concat());
+        state.crossover_stats = crossover.stats();
+        state.mutation_stats = mutation.stats();
+        state.record(&ratings.concat(), everyone.len() * (collaborators + 1));
+
+        if stop_crit.criterion(&state) {
+            return RunResult::new(best_team, best_rating, pops, ratings.concat(), state, stop_crit.reason());
+        }
+
+        // Offspring are not rated yet, the best of this generation stand for
+        // their population
+        representatives = pops.iter().zip(&ratings).map(|(pop, r)| pop[best_index(r).0].clone()).collect();
+        (pops, crossed, mutated) = breed_all(&pops, &ratings, selector, mating, crossover, mutation, pop_size as usize);
+    }
+}
+
+#[cfg(test)]
+struct Gap;
+
+// Higher number wins
+#[cfg(test)]
+impl Contest<i32> for Gap {
+
+    fn contest(&self, first: &i32, second: &i32) -> (f32, f32) {
+        match first.cmp(second) {
+            std::cmp::Ordering::Greater => (1.0, 0.0),
+            std::cmp::Ordering::Less => (0.0, 1.0),
+            std::cmp::Ordering::Equal => (0.5, 0.5)
+        }
+    }
+}
+
+#[test]
+fn test_play() {
+    let pops = vec![vec![1, 5, 3, 4]];
+
+    let (ratings, games) = play(&Gap, &pops, Pairing::RoundRobin, &[], 0);
+    assert_eq!(ratings[0], vec![0.0, 1.0, 1.0 / 3.0, 2.0 / 3.0]);
+    assert_eq!(games, 12);
+
+    let (ratings, games) = play(&Gap, &pops, Pairing::Tournament, &[], 0);
+    assert_eq!(ratings[0][1], 2.0);
+    assert_eq!(ratings[0][0], 0.0);
+    assert_eq!(games, 3);
+
+    // Two populations only play each other, and the hall of fame
+    let pops = vec![vec![1, 2], vec![0, 3]];
+    let (ratings, games) = play(&Gap, &pops, Pairing::RoundRobin, &[(10, 0.0)], 1);
+    assert_eq!(ratings, vec![vec![0.5, 0.5], vec![0.0, 1.0]]);
+    assert_eq!(games, 8 + 4);
+
+    // 3 beats whoever it meets from the other population
+    let (ratings, games) = play(&Gap, &pops, Pairing::Tournament, &[], 0);
+    assert_eq!(ratings[1][1], 2.0);
+    assert_eq!(games, 3);
+
+    // Only one game can be made, the others win nothing
+    let pops = vec![vec![1, 2, 3], vec![0]];
+    let (ratings, games) = play(&Gap, &pops, Pairing::Tournament, &[], 0);
+    assert_eq!(ratings.concat().iter().sum::<f32>(), 1.0);
+    assert_eq!(ratings[1][0], 0.0);
+    assert_eq!(games, 1);
+}
+
+#[cfg(test)]
+struct Digit;
+
+#[cfg(test)]
+impl Generator<i32> for Digit {
+
+    fn generator(&self) -> i32 {
+        use rand::Rng;
+        thread_rng().gen_range(0..10)
+    }
+}
+
+#[cfg(test)]
+struct Keep;
+
+#[cfg(test)]
+impl crate::crossover::Crossover<i32> for Keep {
+
+    fn crossover(&self, parent1: &i32, _parent2: &i32) -> i32 {
+        *parent1
+    }
+}
+
+#[cfg(test)]
+struct Step;
+
+#[cfg(test)]
+impl Mutation<i32> for Step {
+
+    fn mutation(&self, pop: &mut i32) {
+        use rand::Rng;
+        *pop = (*pop + thread_rng().gen_range(-1..=1)).clamp(0, 9);
+    }
+}
+
+#[test]
+fn test_competitive() {
+    use crate::criterion::Iterations;
+    use crate::mating::Random;
+    use crate::selector::Elitism;
+
+    for pairing in [Pairing::RoundRobin, Pairing::Random{ opponents: 5 }, Pairing::Tournament] {
+        let result = competitive(&Digit, &Gap, &Elitism{ max_pop: 5 }, &Random, &Keep, &Step,
+            &mut Iterations::new(100), pairing, 2, 5, 2, 20);
+
+        // The arms race ends at the highest number
+        assert_eq!(result.best, vec![9, 9], "{pairing:?}");
+        assert_eq!(result.generations, 100);
+        assert_eq!((result.population.len(), result.ratings.len()), (2, 40));
+    }
+}
+
+#[test]
+fn test_cooperative() {
+    use crate::criterion::{Iterations, Mark};
+    use crate::mating::Random;
+    use crate::selector::Elitism;
+
+    // Best team: 9, 0, 9
+    struct Pattern;
+
+    impl Collaboration<i32> for Pattern {
+
+        fn collaboration(&self, team: &[&i32]) -> f32 {
+            (*team[0] - *team[1] + *team[2]) as f32
+        }
+    }
+
+    let mut stop_crit = Mark{ max_rating: 18.0 }.or(Iterations::new(500));
+    let result = cooperative(&Digit, &Pattern, &Elitism{ max_pop: 5 }, &Random, &Keep, &Step,
+        &mut stop_crit, 1, 3, 20);
+
+    assert_eq!(result.rating, 18.0);
+    assert_eq!(result.best, vec![9, 0, 9]);
+}
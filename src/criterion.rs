@@ -1,5 +1,49 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::time::Duration;
+
+use crate::state::RunState;
+
 pub trait Criterion {
-    fn criterion(&mut self, ratings: &Vec<f32>) -> bool;
+    fn criterion(&mut self, state: &RunState) -> bool;
+
+    // Why the criterion fired, meant to be read once the run stopped
+    fn reason(&self) -> String {
+        String::from("criterion reached")
+    }
+
+    fn or<C>(self, other: C) -> Any
+    where
+        Self: Sized + 'static,
+        C: Criterion + 'static {
+
+        Any::new(vec![Box::new(self), Box::new(other)])
+    }
+
+    fn and<C>(self, other: C) -> All
+    where
+        Self: Sized + 'static,
+        C: Criterion + 'static {
+
+        All::new(vec![Box::new(self), Box::new(other)])
+    }
+
+    fn not(self) -> Not
+    where
+        Self: Sized + 'static {
+
+        Not::new(Box::new(self))
+    }
+}
+
+impl<C: Criterion + ?Sized> Criterion for Box<C> {
+
+    fn criterion(&mut self, state: &RunState) -> bool {
+        (**self).criterion(state)
+    }
+
+    fn reason(&self) -> String {
+        (**self).reason()
+    }
 }
 
 pub struct Mark {
@@ -8,21 +52,26 @@ pub struct Mark {
 
 impl Criterion for Mark
 {
-    fn criterion(&mut self, ratings: &Vec<f32>) -> bool {
-        for r in ratings {
+    fn criterion(&mut self, state: &RunState) -> bool {
+        for r in &state.ratings {
             if *r >= self.max_rating {
                 return true;
             }
         }
         false
     }
+
+    fn reason(&self) -> String {
+        format!("rating of {} reached", self.max_rating)
+    }
 }
 
+// Fires when the best rating ever seen improved by no more than `epsilon`
+// (relative) over the last `max_iterations` generations
 #[derive(Default)]
 pub struct Plateau {
     pub max_iterations: usize,
-    iterations: usize,
-    prev_rating: f32
+    pub epsilon: f32
 }
 
 impl Plateau {
@@ -30,8 +79,14 @@ impl Plateau {
     pub fn new(max_iterations: usize) -> Self {
         Self {
             max_iterations,
-            iterations: 0,
-            prev_rating: 0.0
+            epsilon: 0.0
+        }
+    }
+
+    pub fn with_epsilon(max_iterations: usize, epsilon: f32) -> Self {
+        Self {
+            max_iterations,
+            epsilon
         }
     }
 }
@@ -39,32 +94,37 @@ impl Plateau {
 impl Criterion for Plateau
 {
 
-    fn criterion(&mut self, ratings: &Vec<f32>) -> bool {
-        let max = ratings.clone().into_iter().reduce(f32::max).unwrap();
-        if self.prev_rating != max {
-            self.prev_rating = max;
-            self.iterations = 0;
-        }
-        else if self.prev_rating == max {
-            self.iterations += 1;
+    fn criterion(&mut self, state: &RunState) -> bool {
+        let history = &state.best_history;
+        if history.len() <= self.max_iterations {
+            return false;
         }
 
-        self.max_iterations <= self.iterations
+        let split = history.len() - self.max_iterations;
+        let before = history[..split].iter().copied().fold(f32::MIN, f32::max);
+        let after = history[split..].iter().copied().fold(before, f32::max);
+
+        (after - before) / before.abs().max(f32::EPSILON) <= self.epsilon
+    }
+
+    fn reason(&self) -> String {
+        format!("best rating improved by at most {} over {} iterations", self.epsilon, self.max_iterations)
     }
 }
 
 #[derive(Default)]
 pub struct Iterations {
-    pub max_iterations: usize,
-    iterations: usize
+    pub max_iterations: usize
 }
 
 impl Criterion for Iterations
 {
-    fn criterion(&mut self, _ratings: &Vec<f32>) -> bool {
-        self.iterations += 1;
+    fn criterion(&mut self, state: &RunState) -> bool {
+        self.max_iterations <= state.generation
+    }
 
-        self.max_iterations <= self.iterations
+    fn reason(&self) -> String {
+        format!("{} iterations reached", self.max_iterations)
     }
 }
 
@@ -72,8 +132,317 @@ impl Iterations {
 
     pub fn new(max_iterations: usize) -> Self {
         Self {
-            max_iterations,
-            iterations: 0,
+            max_iterations
+        }
+    }
+}
+
+pub struct TimeLimit {
+    pub limit: Duration
+}
+
+impl TimeLimit {
+
+    pub fn new(limit: Duration) -> Self {
+        Self {
+            limit
+        }
+    }
+}
+
+impl Criterion for TimeLimit
+{
+    fn criterion(&mut self, state: &RunState) -> bool {
+        state.elapsed >= self.limit
+    }
+
+    fn reason(&self) -> String {
+        format!("time limit of {}ms reached", self.limit.as_millis())
+    }
+}
+
+// Fires once the genotype diversity fell to `min_diversity` or below, never
+// when the runner does not measure it
+pub struct LowDiversity {
+    pub min_diversity: f32
+}
+
+impl Criterion for LowDiversity
+{
+    fn criterion(&mut self, state: &RunState) -> bool {
+        state.diversity().is_some_and(|d| d <= self.min_diversity)
+    }
+
+    fn reason(&self) -> String {
+        format!("diversity fell to {}", self.min_diversity)
+    }
+}
+
+// Fires once `max_evaluations` were spent. Runners only check between
+// generations, so a run goes over the budget by up to a generation: its
+// children, and the local searches and extra evaluations of the runner. For
+// a fair comparison, compare runs on their `evaluations`, not the budget.
+#[derive(Default)]
+pub struct EvaluationBudget {
+    pub max_evaluations: usize
+}
+
+impl EvaluationBudget {
+
+    pub fn new(max_evaluations: usize) -> Self {
+        Self {
+            max_evaluations
+        }
+    }
+}
+
+impl Criterion for EvaluationBudget
+{
+    fn criterion(&mut self, state: &RunState) -> bool {
+        self.max_evaluations <= state.evaluations
+    }
+
+    fn reason(&self) -> String {
+        format!("budget of {} evaluations spent", self.max_evaluations)
+    }
+}
+
+// Clones share the same flag, hand one to another thread (or a Ctrl-C
+// handler) and call `cancel` on it to stop the run
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    flag: Arc<AtomicBool>
+}
+
+impl CancellationToken {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+impl Criterion for CancellationToken
+{
+    fn criterion(&mut self, _state: &RunState) -> bool {
+        self.is_cancelled()
+    }
+
+    fn reason(&self) -> String {
+        String::from("cancelled")
+    }
+}
+
+// Fires as soon as one of the criteria fires. Every criterion is still checked
+// each time so the stateful ones (Plateau, Iterations...) keep counting.
+pub struct Any {
+    pub criteria: Vec<Box<dyn Criterion>>,
+    fired: Vec<usize>
+}
+
+impl Any {
+
+    pub fn new(criteria: Vec<Box<dyn Criterion>>) -> Self {
+        Self {
+            criteria,
+            fired: vec![]
+        }
+    }
+
+    // Indices of the criteria that fired on the last check
+    pub fn fired(&self) -> &[usize] {
+        &self.fired
+    }
+}
+
+impl Criterion for Any
+{
+    fn criterion(&mut self, state: &RunState) -> bool {
+        self.fired.clear();
+        for (i, c) in self.criteria.iter_mut().enumerate() {
+            if c.criterion(state) {
+                self.fired.push(i);
+            }
         }
+
+        !self.fired.is_empty()
+    }
+
+    fn reason(&self) -> String {
+        self.fired.iter()
+            .map(|i| self.criteria[*i].reason())
+            .collect::<Vec<_>>()
+            .join(" and ")
     }
-}
\ No newline at end of file
+
+    fn or<C>(mut self, other: C) -> Any
+    where
+        C: Criterion + 'static {
+
+        self.criteria.push(Box::new(other));
+        self
+    }
+}
+
+// Fires once every criterion fires on the same check
+pub struct All {
+    pub criteria: Vec<Box<dyn Criterion>>,
+    fired: Vec<usize>
+}
+
+impl All {
+
+    pub fn new(criteria: Vec<Box<dyn Criterion>>) -> Self {
+        Self {
+            criteria,
+            fired: vec![]
+        }
+    }
+
+    // Indices of the criteria that fired on the last check
+    pub fn fired(&self) -> &[usize] {
+        &self.fired
+    }
+}
+
+impl Criterion for All
+{
+    fn criterion(&mut self, state: &RunState) -> bool {
+        self.fired.clear();
+        for (i, c) in self.criteria.iter_mut().enumerate() {
+            if c.criterion(state) {
+                self.fired.push(i);
+            }
+        }
+
+        self.fired.len() == self.criteria.len()
+    }
+
+    fn reason(&self) -> String {
+        self.criteria.iter()
+            .map(|c| c.reason())
+            .collect::<Vec<_>>()
+            .join(" and ")
+    }
+
+    fn and<C>(mut self, other: C) -> All
+    where
+        C: Criterion + 'static {
+
+        self.criteria.push(Box::new(other));
+        self
+    }
+}
+
+pub struct Not {
+    pub criterion: Box<dyn Criterion>
+}
+
+impl Not {
+
+    pub fn new(criterion: Box<dyn Criterion>) -> Self {
+        Self {
+            criterion
+        }
+    }
+}
+
+impl Criterion for Not
+{
+    fn criterion(&mut self, state: &RunState) -> bool {
+        !self.criterion.criterion(state)
+    }
+
+    fn reason(&self) -> String {
+        format!("not ({})", self.criterion.reason())
+    }
+}
+
+#[cfg(test)]
+fn run_state(history: &[&[f32]], evaluations: usize) -> RunState {
+    let mut state = RunState::new();
+    for ratings in history {
+        state.record(ratings, evaluations);
+    }
+
+    state
+}
+
+#[test]
+fn test_any_reports_fired() {
+    let mut crit = Mark{ max_rating: 10.0 }.or(Iterations::new(2)).or(Plateau::new(100));
+
+    assert!(!crit.criterion(&run_state(&[&[1.0, 2.0]], 2)));
+    assert!(!crit.criterion(&run_state(&[&[1.0, 2.0], &[1.0, 3.0]], 2)));
+    assert!(crit.criterion(&run_state(&[&[1.0, 2.0], &[1.0, 3.0], &[1.0, 4.0]], 2)));
+    assert_eq!(crit.fired(), &[1]);
+    assert_eq!(crit.reason(), "2 iterations reached");
+}
+
+#[test]
+fn test_plateau_epsilon() {
+    let mut crit = Plateau::with_epsilon(2, 0.1);
+
+    assert!(!crit.criterion(&run_state(&[&[10.0], &[10.5]], 1)));
+    assert!(!crit.criterion(&run_state(&[&[10.0], &[10.5], &[12.0]], 1)));
+    assert!(crit.criterion(&run_state(&[&[10.0], &[10.5], &[10.8]], 1)));
+}
+
+#[test]
+fn test_evaluation_budget() {
+    let mut crit = EvaluationBudget::new(5);
+
+    assert!(!crit.criterion(&run_state(&[&[1.0, 2.0]], 2)));
+    assert!(!crit.criterion(&run_state(&[&[1.0, 2.0]], 4)));
+    assert!(crit.criterion(&run_state(&[&[1.0, 2.0]], 5)));
+}
+
+#[test]
+fn test_time_limit() {
+    let mut crit = TimeLimit::new(Duration::from_millis(20));
+    let mut state = run_state(&[&[1.0]], 1);
+
+    assert!(!crit.criterion(&state));
+    std::thread::sleep(Duration::from_millis(25));
+    state.record(&[1.0], 1);
+    assert!(crit.criterion(&state));
+    assert_eq!(crit.reason(), "time limit of 20ms reached");
+}
+
+#[test]
+fn test_cancellation_token() {
+    let mut crit = CancellationToken::new();
+    let token = crit.clone();
+    let state = RunState::new();
+
+    assert!(!crit.criterion(&state));
+    std::thread::spawn(move || token.cancel()).join().unwrap();
+    assert!(crit.criterion(&state));
+}
+
+#[test]
+fn test_all_and_not() {
+    let mut crit = Mark{ max_rating: 10.0 }.and(Iterations::new(1).not());
+
+    assert!(crit.criterion(&run_state(&[&[12.0]], 1)));
+    assert!(!crit.criterion(&run_state(&[&[12.0], &[12.0]], 1)));
+}
+
+#[test]
+fn test_low_diversity() {
+    let mut crit = LowDiversity{ min_diversity: 1.0 };
+    let mut state = run_state(&[&[1.0]], 1);
+
+    assert!(!crit.criterion(&state));
+    state.diversity_history.push(3.0);
+    assert!(!crit.criterion(&state));
+    state.diversity_history.push(0.5);
+    assert!(crit.criterion(&state));
+}
@@ -1,3 +1,248 @@
+use rand::{thread_rng, Rng, distributions::WeightedIndex, prelude::Distribution};
+
+use crate::state::OperatorStats;
+
 pub trait Crossover<T> {
     fn crossover(&self, parent1: &T, parent2: &T) -> T;
+
+    // Shown in the stats of adaptive operators, the type name by default
+    fn name(&self) -> String {
+        crate::short_type_name::<Self>()
+    }
+
+    // Children of each crossover made by the runners, 1 or 2. Operators
+    // naturally giving two children return 2 and override `crossover_pair`.
+    fn children(&self) -> usize {
+        1
+    }
+
+    fn crossover_pair(&self, parent1: &T, parent2: &T) -> (T, T) {
+        (self.crossover(parent1, parent2), self.crossover(parent2, parent1))
+    }
+
+    // How much the best child of each crossover improved on its best parent,
+    // in call order. Only adaptive operators care.
+    fn credit(&self, _improvements: &[f32]) {}
+
+    fn stats(&self) -> Vec<OperatorStats> {
+        vec![]
+    }
+}
+
+// For operators working on any number of parents and/or giving several
+// children. Every `Crossover` is a `MultiCrossover` with 2 parents giving
+// the child of `crossover`, or the 2 of `crossover_pair` when `children` is
+// 2. Because of that blanket impl a type cannot
+// implement both traits: implement `Crossover` for 2 parents, `MultiCrossover`
+// otherwise. `multi_crossover` must give at least one child, the runners
+// fill their generations with them.
+pub trait MultiCrossover<T> {
+    fn parents(&self) -> usize;
+
+    fn multi_crossover(&self, parents: &[&T]) -> Vec<T>;
+
+    fn credit(&self, _improvements: &[f32]) {}
+
+    fn stats(&self) -> Vec<OperatorStats> {
+        vec![]
+    }
+}
+
+impl<T, C: Crossover<T>> MultiCrossover<T> for C {
+
+    fn parents(&self) -> usize {
+        2
+    }
+
+    fn multi_crossover(&self, parents: &[&T]) -> Vec<T> {
+        if self.children() < 2 {
+            return vec![self.crossover(parents[0], parents[1])];
+        }
+
+        let (child1, child2) = self.crossover_pair(parents[0], parents[1]);
+        vec![child1, child2]
+    }
+
+    fn credit(&self, improvements: &[f32]) {
+        Crossover::credit(self, improvements);
+    }
+
+    fn stats(&self) -> Vec<OperatorStats> {
+        Crossover::stats(self)
+    }
+}
+
+// Uses one of the crossovers, picked according to its weight. Weights can't
+// be negative and at least one must be positive.
+pub struct OneOf<T> {
+    operators: Vec<Box<dyn Crossover<T>>>,
+    weights: WeightedIndex<f64>
+}
+
+impl<T> OneOf<T> {
+
+    pub fn new(operators: Vec<(f64, Box<dyn Crossover<T>>)>) -> Self {
+        let (weights, operators): (Vec<f64>, Vec<_>) = operators.into_iter().unzip();
+
+        Self {
+            operators,
+            weights: WeightedIndex::new(weights).expect("OneOf needs valid weights, with at least one above 0")
+        }
+    }
+
+    fn pick(&self) -> &dyn Crossover<T> {
+        self.operators[self.weights.sample(&mut thread_rng())].as_ref()
+    }
+}
+
+impl<T> Crossover<T> for OneOf<T>
+{
+    fn crossover(&self, parent1: &T, parent2: &T) -> T {
+        self.pick().crossover(parent1, parent2)
+    }
+
+    fn children(&self) -> usize {
+        self.operators.iter().map(|c| c.children()).max().unwrap_or(1)
+    }
+
+    fn crossover_pair(&self, parent1: &T, parent2: &T) -> (T, T) {
+        self.pick().crossover_pair(parent1, parent2)
+    }
+}
+
+// Each crossover recombines the two children of the previous one
+pub struct Sequence<T>(pub Vec<Box<dyn Crossover<T>>>);
+
+impl<T: Clone> Crossover<T> for Sequence<T>
+{
+    fn crossover(&self, parent1: &T, parent2: &T) -> T {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        self.0.iter().map(|c| c.children()).max().unwrap_or(1)
+    }
+
+    fn crossover_pair(&self, parent1: &T, parent2: &T) -> (T, T) {
+        let mut children = (parent1.clone(), parent2.clone());
+        for c in &self.0 {
+            children = c.crossover_pair(&children.0, &children.1);
+        }
+
+        children
+    }
+}
+
+// Otherwise the parents are copied as is
+pub struct WithProbability<C>(pub f64, pub C);
+
+impl<T: Clone, C: Crossover<T>> Crossover<T> for WithProbability<C>
+{
+    fn crossover(&self, parent1: &T, parent2: &T) -> T {
+        if thread_rng().gen_bool(self.0) {
+            self.1.crossover(parent1, parent2)
+        } else {
+            parent1.clone()
+        }
+    }
+
+    fn children(&self) -> usize {
+        self.1.children()
+    }
+
+    fn crossover_pair(&self, parent1: &T, parent2: &T) -> (T, T) {
+        if thread_rng().gen_bool(self.0) {
+            self.1.crossover_pair(parent1, parent2)
+        } else {
+            (parent1.clone(), parent2.clone())
+        }
+    }
+}
+
+// Recombines the children again, n times in total
+pub struct Repeat<C>(pub usize, pub C);
+
+impl<T: Clone, C: Crossover<T>> Crossover<T> for Repeat<C>
+{
+    fn crossover(&self, parent1: &T, parent2: &T) -> T {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        self.1.children()
+    }
+
+    fn crossover_pair(&self, parent1: &T, parent2: &T) -> (T, T) {
+        let mut children = (parent1.clone(), parent2.clone());
+        for _ in 0..self.0 {
+            children = self.1.crossover_pair(&children.0, &children.1);
+        }
+
+        children
+    }
+}
+
+#[test]
+fn test_multi_crossover() {
+    struct Average;
+
+    impl MultiCrossover<f32> for Average {
+
+        fn parents(&self) -> usize {
+            3
+        }
+
+        fn multi_crossover(&self, parents: &[&f32]) -> Vec<f32> {
+            let mean = parents.iter().copied().sum::<f32>() / parents.len() as f32;
+            vec![mean, mean]
+        }
+    }
+
+    struct First;
+
+    impl Crossover<f32> for First {
+
+        fn crossover(&self, parent1: &f32, _parent2: &f32) -> f32 {
+            *parent1
+        }
+    }
+
+    assert_eq!(Average.multi_crossover(&[&1.0, &2.0, &3.0]), vec![2.0, 2.0]);
+    assert_eq!(MultiCrossover::parents(&First), 2);
+    assert_eq!(First.multi_crossover(&[&1.0, &2.0]), vec![1.0]);
+}
+
+#[test]
+fn test_combinators() {
+    struct Sum;
+
+    impl Crossover<i32> for Sum {
+
+        fn crossover(&self, parent1: &i32, parent2: &i32) -> i32 {
+            parent1 + parent2
+        }
+    }
+
+    let crossover = Sequence(vec![
+        Box::new(Repeat(2, Sum)),
+        Box::new(WithProbability(0.0, Sum))
+    ]);
+    // (1, 2) -> (3, 3) -> (6, 6)
+    assert_eq!(crossover.crossover_pair(&1, &2), (6, 6));
+    assert_eq!(OneOf::new(vec![(1.0, Box::new(Sum) as Box<dyn Crossover<i32>>)]).crossover(&1, &2), 3);
+}
+
+#[test]
+#[should_panic(expected = "valid weights")]
+fn test_one_of_weights() {
+    struct First;
+
+    impl Crossover<i32> for First {
+
+        fn crossover(&self, parent1: &i32, _parent2: &i32) -> i32 {
+            *parent1
+        }
+    }
+
+    OneOf::new(vec![(0.0, Box::new(First) as Box<dyn Crossover<i32>>)]);
 }
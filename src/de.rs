@@ -0,0 +1,302 @@
+use rand::{thread_rng, Rng, seq::index::sample};
+
+use crate::criterion::Criterion;
+use crate::evaluator::Evaluator;
+use crate::generator::Generator;
+use crate::genome::real::{standard_normal, Bounds};
+use crate::result::RunResult;
+use crate::state::RunState;
+use crate::{best_index, fill_ratings};
+
+// How the donor vector of each target x_i is built, r1, r2, r3 being distinct
+// random individuals other than i
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Variant {
+    // DE/rand/1: x_r1 + F * (x_r2 - x_r3)
+    Rand1,
+    // DE/best/1: x_best + F * (x_r1 - x_r2)
+    Best1,
+    // DE/current-to-best/1: x_i + F * (x_best - x_i) + F * (x_r1 - x_r2)
+    CurrentToBest1,
+    // DE/current-to-pbest/1 as in SHADE (without the archive), x_best being
+    // drawn among the best `p` fraction of the population
+    CurrentToPBest1 { p: f64 }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Recombination {
+    // Each gene comes from the donor with probability CR, one at least
+    Binomial,
+    // A run of consecutive genes, starting at a random one, comes from the
+    // donor while draws stay under CR
+    Exponential
+}
+
+// Where F (scale factor) and CR (crossover rate) come from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Control {
+    Fixed { f: f64, cr: f64 },
+    // jDE (Brest et al.): every individual carries its own F and CR, drawn
+    // again with probability tau1 and tau2 before making its trial, kept when
+    // the trial replaces it
+    JDe { tau1: f64, tau2: f64 },
+    // SHADE (Tanabe and Fukunaga): F and CR are sampled around a memory of
+    // `memory` entries, updated with the weighted means of the successful ones
+    Shade { memory: usize }
+}
+
+// Parameter state of a run, following `Control`
+struct Parameters {
+    control: Control,
+    f: Vec<f64>,
+    cr: Vec<f64>,
+    memory_f: Vec<f64>,
+    memory_cr: Vec<f64>,
+    next_memory: usize
+}
+
+impl Parameters {
+
+    fn new(control: Control, pop_size: usize) -> Self {
+        let (f, cr, memory) = match control {
+            Control::Fixed { f, cr } => (f, cr, 0),
+            Control::JDe { .. } => (0.5, 0.9, 0),
+            Control::Shade { memory } => (0.5, 0.5, memory.max(1))
+        };
+
+        Self {
+            control,
+            f: vec![f; pop_size],
+            cr: vec![cr; pop_size],
+            memory_f: vec![0.5; memory],
+            memory_cr: vec![0.5; memory],
+            next_memory: 0
+        }
+    }
+
+    // F and CR for the trial of every individual
+    fn sample<R: Rng>(&self, rng: &mut R) -> (Vec<f64>, Vec<f64>) {
+        match self.control {
+            Control::Fixed { .. } => (self.f.clone(), self.cr.clone()),
+            Control::JDe { tau1, tau2 } => {
+                let f = self.f.iter()
+                    .map(|f| if rng.gen_bool(tau1) { 0.1 + 0.9 * rng.gen::<f64>() } else { *f })
+                    .collect();
+                let cr = self.cr.iter()
+                    .map(|cr| if rng.gen_bool(tau2) { rng.gen() } else { *cr })
+                    .collect();
+                (f, cr)
+            },
+            Control::Shade { .. } => (0..self.f.len()).map(|_| {
+                let k = rng.gen_range(0..self.memory_f.len());
+                let cr = (self.memory_cr[k] + 0.1 * standard_normal(rng)).clamp(0.0, 1.0);
+                // Cauchy draws, again while not positive
+                let f = loop {
+                    let f = self.memory_f[k] + 0.1 * (std::f64::consts::PI * (rng.gen::<f64>() - 0.5)).tan();
+                    if f > 0.0 {
+                        break f.min(1.0);
+                    }
+                };
+                (f, cr)
+            }).unzip()
+        }
+    }
+
+    // `successes` holds the individuals replaced by their trial, with the
+    // rating improvement
+    fn update(&mut self, f: &[f64], cr: &[f64], successes: &[(usize, f32)]) {
+        match self.control {
+            Control::Fixed { .. } => {},
+            Control::JDe { .. } => {
+                for (i, _) in successes {
+                    self.f[*i] = f[*i];
+                    self.cr[*i] = cr[*i];
+                }
+            },
+            Control::Shade { .. } => {
+                let total: f64 = successes.iter().map(|(_, d)| *d as f64).sum();
+                if successes.is_empty() || total <= 0.0 {
+                    return;
+                }
+
+                let weight = |d: f32| d as f64 / total;
+                let mean_cr: f64 = successes.iter().map(|(i, d)| weight(*d) * cr[*i]).sum();
+                let sum_f2: f64 = successes.iter().map(|(i, d)| weight(*d) * f[*i] * f[*i]).sum();
+                let sum_f: f64 = successes.iter().map(|(i, d)| weight(*d) * f[*i]).sum();
+
+                self.memory_cr[self.next_memory] = mean_cr;
+                // Weighted Lehmer mean
+                self.memory_f[self.next_memory] = sum_f2 / sum_f;
+                self.next_memory = (self.next_memory + 1) % self.memory_f.len();
+            }
+        }
+    }
+}
+
+fn donor<R: Rng>(rng: &mut R, variant: Variant, pop: &[Vec<f64>], order: &[usize], i: usize, f: f64) -> Vec<f64> {
+    // Three distinct indices other than i
+    let mut r = sample(rng, pop.len() - 1, 3).into_vec();
+    r.iter_mut().filter(|r| **r >= i).for_each(|r| *r += 1);
+    let (x, a, b, c) = (&pop[i], &pop[r[0]], &pop[r[1]], &pop[r[2]]);
+
+    match variant {
+        Variant::Rand1 => (0..x.len()).map(|j| a[j] + f * (b[j] - c[j])).collect(),
+        Variant::Best1 => {
+            let best = &pop[order[0]];
+            (0..x.len()).map(|j| best[j] + f * (a[j] - b[j])).collect()
+        },
+        Variant::CurrentToBest1 | Variant::CurrentToPBest1 { .. } => {
+            let best = match variant {
+                Variant::CurrentToPBest1 { p } => {
+                    let top = ((p * pop.len() as f64).ceil() as usize).clamp(1, pop.len());
+                    &pop[order[rng.gen_range(0..top)]]
+                },
+                _ => &pop[order[0]]
+            };
+            (0..x.len()).map(|j| x[j] + f * (best[j] - x[j]) + f * (a[j] - b[j])).collect()
+        }
+    }
+}
+
+fn recombine<R: Rng>(rng: &mut R, recombination: Recombination, target: &[f64], donor: &[f64], cr: f64) -> Vec<f64> {
+    let len = target.len();
+    let mut trial = target.to_vec();
+    if len == 0 {
+        return trial;
+    }
+
+    let start = rng.gen_range(0..len);
+    match recombination {
+        Recombination::Binomial => {
+            for j in 0..len {
+                if j == start || rng.gen::<f64>() < cr {
+                    trial[j] = donor[j];
+                }
+            }
+        },
+        Recombination::Exponential => {
+            let mut j = start;
+            loop {
+                trial[j] = donor[j];
+                j = (j + 1) % len;
+                if j == start || rng.gen::<f64>() >= cr {
+                    break;
+                }
+            }
+        }
+    }
+
+    trial
+}
+
+// Differential evolution over real genomes, for the same generators and
+// evaluators as the GA. Each generation every individual makes a trial that
+// replaces it if rated at least as well. Trials out of `bounds` are repaired.
+#[allow(clippy::too_many_arguments)]
+pub fn differential_evolution<G, E, F>(generator: &G, evaluator: &E, stop_crit: &mut F, bounds: &Bounds,
+    variant: Variant, recombination: Recombination, control: Control, pop_size: u32) -> RunResult<Vec<f64>>
+where
+    G: Generator<Vec<f64>>,
+    E: Evaluator<Vec<f64>> + Send + Sync,
+    F: Criterion {
+
+    assert!(pop_size >= 4, "differential evolution needs at least 4 individuals");
+
+    let mut rng = thread_rng();
+
+    let mut state = RunState::new();
+
+    let mut pop: Vec<Vec<f64>> = (0..pop_size).map(|_| generator.generator()).collect();
+    let mut ratings = Vec::with_capacity(pop_size as usize);
+    fill_ratings(pop_size, &pop, evaluator, &mut ratings);
+
+    let (index, mut best_rating) = best_index(&ratings);
+    let mut best_ever = pop[index].clone();
+
+    state.record(&ratings, pop_size as usize);
+
+    let mut parameters = Parameters::new(control, pop_size as usize);
+    while !stop_crit.criterion(&state) {
+        let mut order: Vec<usize> = (0..pop.len()).collect();
+        order.sort_by(|i, j| ratings[*j].total_cmp(&ratings[*i]));
+
+        let (f, cr) = parameters.sample(&mut rng);
+        let trials: Vec<Vec<f64>> = (0..pop.len()).map(|i| {
+            let donor = donor(&mut rng, variant, &pop, &order, i, f[i]);
+            let mut trial = recombine(&mut rng, recombination, &pop[i], &donor, cr[i]);
+            bounds.repair(&mut trial);
+            trial
+        }).collect();
+
+        let mut trial_ratings = Vec::with_capacity(pop_size as usize);
+        fill_ratings(pop_size, &trials, evaluator, &mut trial_ratings);
+
+        let mut successes = vec![];
+        for (i, (trial, rating)) in trials.into_iter().zip(trial_ratings).enumerate() {
+            if rating >= ratings[i] {
+                successes.push((i, rating - ratings[i]));
+                pop[i] = trial;
+                ratings[i] = rating;
+            }
+        }
+        parameters.update(&f, &cr, &successes);
+
+        let (index, best) = best_index(&ratings);
+        if best > best_rating {
+            best_ever = pop[index].clone();
+            best_rating = best;
+        }
+        state.record(&ratings, pop_size as usize);
+    }
+
+    RunResult::new(best_ever, best_rating, pop, ratings, state, stop_crit.reason())
+}
+
+#[test]
+fn test_recombine() {
+    let mut rng = thread_rng();
+    let (target, donor) = (vec![0.0; 8], vec![1.0; 8]);
+
+    for recombination in [Recombination::Binomial, Recombination::Exponential] {
+        assert_eq!(recombine(&mut rng, recombination, &target, &donor, 1.0), donor);
+        // At least one gene always comes from the donor
+        let trial = recombine(&mut rng, recombination, &target, &donor, 0.0);
+        assert_eq!(trial.iter().sum::<f64>(), 1.0);
+    }
+}
+
+#[test]
+fn test_differential_evolution() {
+    use crate::criterion::{Iterations, Mark};
+    use crate::genome::real::{Repair, UniformReal};
+
+    struct Sphere;
+
+    impl Evaluator<Vec<f64>> for Sphere {
+
+        fn evaluator(&self, pop: &Vec<f64>) -> f32 {
+            -pop.iter().map(|x| (x - 1.0).powi(2)).sum::<f64>() as f32
+        }
+    }
+
+    let bounds = Bounds::uniform(5, -5.0, 5.0, Repair::Reflect);
+    let generator = UniformReal{ bounds: bounds.clone() };
+    // Best1 bases every donor on the best individual, so the population
+    // contracts around it and the difference vectors shrink with it. Below
+    // F = 0.8 they can vanish before the optimum is reached on this problem,
+    // and the run stagnates: the larger scale keeps the steps exploring.
+    let setups = [
+        (Variant::Rand1, Recombination::Exponential, Control::Fixed{ f: 0.5, cr: 0.9 }),
+        (Variant::Best1, Recombination::Binomial, Control::Fixed{ f: 0.8, cr: 0.9 }),
+        (Variant::CurrentToBest1, Recombination::Binomial, Control::JDe{ tau1: 0.1, tau2: 0.1 }),
+        (Variant::CurrentToPBest1{ p: 0.1 }, Recombination::Binomial, Control::Shade{ memory: 5 })
+    ];
+
+    for (variant, recombination, control) in setups {
+        let mut stop_crit = Mark{ max_rating: -1e-6 }.or(Iterations::new(2000));
+        let result = differential_evolution(&generator, &Sphere, &mut stop_crit, &bounds,
+            variant, recombination, control, 30);
+
+        assert!(result.rating >= -1e-6, "{variant:?} {recombination:?} {control:?}: {}", result.rating);
+    }
+}
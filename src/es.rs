@@ -0,0 +1,367 @@
+use rand::{thread_rng, seq::index::sample};
+
+use crate::criterion::Criterion;
+use crate::crossover::MultiCrossover;
+use crate::evaluator::Evaluator;
+use crate::generator::Generator;
+use crate::genome::real::standard_normal;
+use crate::genome::self_adaptive::{SelfAdaptive, StepSizes};
+use crate::mutation::Mutation;
+use crate::result::RunResult;
+use crate::state::RunState;
+use crate::{best_index, credit, fill_ratings};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+    // (mu, lambda): the next parents are the best mu offspring
+    Comma,
+    // (mu + lambda): parents compete with their offspring
+    Plus
+}
+
+// Global intermediate recombination, every gene and step size is the mean of
+// the `rho` parents'
+pub struct Intermediate {
+    pub rho: usize
+}
+
+fn mean(values: impl Iterator<Item = f64>, count: usize) -> f64 {
+    values.sum::<f64>() / count.max(1) as f64
+}
+
+impl MultiCrossover<Vec<f64>> for Intermediate {
+
+    fn parents(&self) -> usize {
+        self.rho
+    }
+
+    fn multi_crossover(&self, parents: &[&Vec<f64>]) -> Vec<Vec<f64>> {
+        let len = parents.iter().map(|p| p.len()).min().unwrap_or(0);
+
+        vec![(0..len).map(|i| mean(parents.iter().map(|p| p[i]), parents.len())).collect()]
+    }
+}
+
+impl MultiCrossover<StepSizes> for Intermediate {
+
+    fn parents(&self) -> usize {
+        self.rho
+    }
+
+    fn multi_crossover(&self, parents: &[&StepSizes]) -> Vec<StepSizes> {
+        let genomes: Vec<&Vec<f64>> = parents.iter().map(|p| &p.genome).collect();
+        let strategies: Vec<&Vec<f64>> = parents.iter().map(|p| &p.strategy).collect();
+
+        vec![SelfAdaptive {
+            genome: self.multi_crossover(&genomes).remove(0),
+            strategy: self.multi_crossover(&strategies).remove(0)
+        }]
+    }
+}
+
+// (mu/rho, lambda) and (mu/rho + lambda) evolution strategies, rho being the
+// number of parents of `crossover`. Each of the lambda offspring comes from
+// rho distinct parents drawn uniformly among the mu, and is always mutated.
+// With `StepSizes` individuals and `LogNormalStepSizes` this is the classic
+// self-adaptive ES.
+#[allow(clippy::too_many_arguments)]
+pub fn evolution_strategy<T, G, E, C, M, F>(generator: &G, evaluator: &E, crossover: &C, mutation: &M,
+    stop_crit: &mut F, mu: u32, lambda: u32, selection: Selection) -> RunResult<T>
+where
+    G: Generator<T>,
+    E: Evaluator<T> + Send + Sync,
+    C: MultiCrossover<T>,
+    M: Mutation<T>,
+    F: Criterion,
+    T: Clone + Send + Sync {
+
+    assert!(selection == Selection::Plus || lambda >= mu, "(mu, lambda) selection needs lambda >= mu");
+
+    let mut rng = thread_rng();
+
+    let mut state = RunState::new();
+
+    let mut pop: Vec<T> = (0..mu).map(|_| generator.generator()).collect();
+    let mut ratings = Vec::with_capacity(mu as usize);
+    fill_ratings(mu, &pop, evaluator, &mut ratings);
+
+    let (index, mut best_rating) = best_index(&ratings);
+    let mut best_ever = pop[index].clone();
+
+    state.record(&ratings, mu as usize);
+
+    let rho = crossover.parents().min(mu as usize);
+    while !stop_crit.criterion(&state) {
+        let mut offspring = Vec::with_capacity(lambda as usize);
+        let (mut crossed, mut mutated) = (vec![], vec![]);
+        while offspring.len() < lambda as usize {
+            let mates = sample(&mut rng, mu as usize, rho);
+            let parent_rating = mates.iter().map(|i| ratings[i]).fold(f32::MIN, f32::max);
+            let mates: Vec<&T> = mates.into_iter().map(|i| &pop[i]).collect();
+            let first = offspring.len();
+
+            let children = crossover.multi_crossover(&mates);
+            assert!(!children.is_empty(), "crossover gave no child, the offspring could never be filled");
+            for mut child in children {
+                if offspring.len() == lambda as usize {
+                    break;
+                }
+                mutation.mutation(&mut child);
+                mutated.push((parent_rating, offspring.len()));
+                offspring.push(child);
+            }
+            crossed.push((parent_rating, first..offspring.len()));
+        }
+
+        let mut offspring_ratings = Vec::with_capacity(lambda as usize);
+        fill_ratings(lambda, &offspring, evaluator, &mut offspring_ratings);
+        credit(crossover, mutation, crossed, &mutated, &offspring_ratings);
+        state.crossover_stats = crossover.stats();
+        state.mutation_stats = mutation.stats();
+
+        let (index, best) = best_index(&offspring_ratings);
+        if best > best_rating {
+            best_ever = offspring[index].clone();
+            best_rating = best;
+        }
+        state.record(&offspring_ratings, lambda as usize);
+
+        let mut candidates: Vec<(T, f32)> = offspring.into_iter().zip(offspring_ratings).collect();
+        if selection == Selection::Plus {
+            candidates.extend(pop.drain(..).zip(ratings.drain(..)));
+        }
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        candidates.truncate(mu as usize);
+        (pop, ratings) = candidates.into_iter().unzip();
+    }
+
+    RunResult::new(best_ever, best_rating, pop, ratings, state, stop_crit.reason())
+}
+
+// Default offspring count of CMA-ES for a problem of `dim` variables
+pub fn default_lambda(dim: usize) -> u32 {
+    4 + (3.0 * (dim as f64).ln()).floor() as u32
+}
+
+// CMA-ES with cumulative step-size adaptation, rank-one and rank-mu updates
+// of the covariance matrix (Hansen, The CMA Evolution Strategy: A Tutorial).
+// Starts from `mean` with step size `sigma`, the best half of the `lambda`
+// samples is recombined each generation. Bounds are left to the evaluator.
+pub fn cma_es<E, F>(evaluator: &E, stop_crit: &mut F, mean: Vec<f64>, sigma: f64, lambda: u32) -> RunResult<Vec<f64>>
+where
+    E: Evaluator<Vec<f64>> + Send + Sync,
+    F: Criterion {
+
+    let n = mean.len();
+    let nf = n as f64;
+    let lambda = lambda.max(2);
+    let mu = lambda as usize / 2;
+
+    let mut weights: Vec<f64> = (1..=mu).map(|i| (mu as f64 + 0.5).ln() - (i as f64).ln()).collect();
+    let total: f64 = weights.iter().sum();
+    weights.iter_mut().for_each(|w| *w /= total);
+    let mueff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+
+    let cc = (4.0 + mueff / nf) / (nf + 4.0 + 2.0 * mueff / nf);
+    let cs = (mueff + 2.0) / (nf + mueff + 5.0);
+    let c1 = 2.0 / ((nf + 1.3).powi(2) + mueff);
+    let cmu = (1.0 - c1).min(2.0 * (mueff - 2.0 + 1.0 / mueff) / ((nf + 2.0).powi(2) + mueff));
+    let damps = 1.0 + 2.0 * (((mueff - 1.0) / (nf + 1.0)).sqrt() - 1.0).max(0.0) + cs;
+    let chi_n = nf.sqrt() * (1.0 - 1.0 / (4.0 * nf) + 1.0 / (21.0 * nf * nf));
+
+    let mut rng = thread_rng();
+    let (mut mean, mut sigma) = (mean, sigma);
+    let (mut pc, mut ps) = (vec![0.0; n], vec![0.0; n]);
+    let mut c = identity(n);
+    // C = B * diag(d)^2 * B^T
+    let (mut b, mut d) = (identity(n), vec![1.0; n]);
+    let mut last_decomposition = 0;
+
+    let mut state = RunState::new();
+    let (mut best_ever, mut best_rating) = (mean.clone(), f32::MIN);
+
+    loop {
+        // Steps from the mean, in the frame of C
+        let steps: Vec<Vec<f64>> = (0..lambda).map(|_| {
+            let z: Vec<f64> = d.iter().map(|d| d * standard_normal(&mut rng)).collect();
+            mat_vec(&b, &z)
+        }).collect();
+        let samples: Vec<Vec<f64>> = steps.iter()
+            .map(|y| mean.iter().zip(y).map(|(m, y)| m + sigma * y).collect())
+            .collect();
+
+        let mut ratings = Vec::with_capacity(lambda as usize);
+        fill_ratings(lambda, &samples, evaluator, &mut ratings);
+
+        let (index, best) = best_index(&ratings);
+        if best > best_rating {
+            best_ever = samples[index].clone();
+            best_rating = best;
+        }
+        state.record(&ratings, lambda as usize);
+        if stop_crit.criterion(&state) {
+            return RunResult::new(best_ever, best_rating, samples, ratings, state, stop_crit.reason());
+        }
+
+        let mut order: Vec<usize> = (0..lambda as usize).collect();
+        order.sort_by(|i, j| ratings[*j].total_cmp(&ratings[*i]));
+        let selected = &order[..mu];
+
+        let mut y_w = vec![0.0; n];
+        for (w, i) in weights.iter().zip(selected) {
+            y_w.iter_mut().zip(&steps[*i]).for_each(|(a, y)| *a += w * y);
+        }
+        mean.iter_mut().zip(&y_w).for_each(|(m, y)| *m += sigma * y);
+
+        // C^-1/2 * y_w = B * diag(1/d) * B^T * y_w
+        let rotated: Vec<f64> = mat_t_vec(&b, &y_w).iter().zip(&d).map(|(v, d)| v / d).collect();
+        let whitened = mat_vec(&b, &rotated);
+        let scale = (cs * (2.0 - cs) * mueff).sqrt();
+        ps.iter_mut().zip(&whitened).for_each(|(p, w)| *p = (1.0 - cs) * *p + scale * w);
+
+        let ps_norm = ps.iter().map(|p| p * p).sum::<f64>().sqrt();
+        let generations = state.generation as i32 + 1;
+        let hsig = ps_norm / (1.0 - (1.0 - cs).powi(2 * generations)).sqrt() / chi_n < 1.4 + 2.0 / (nf + 1.0);
+        let hsig = if hsig { 1.0 } else { 0.0 };
+
+        let scale = (cc * (2.0 - cc) * mueff).sqrt();
+        pc.iter_mut().zip(&y_w).for_each(|(p, y)| *p = (1.0 - cc) * *p + hsig * scale * y);
+
+        let correction = (1.0 - hsig) * cc * (2.0 - cc);
+        for r in 0..n {
+            for col in 0..n {
+                let rank_mu: f64 = weights.iter().zip(selected)
+                    .map(|(w, i)| w * steps[*i][r] * steps[*i][col])
+                    .sum();
+                c[r][col] = (1.0 - c1 - cmu) * c[r][col]
+                    + c1 * (pc[r] * pc[col] + correction * c[r][col])
+                    + cmu * rank_mu;
+            }
+        }
+
+        sigma *= ((cs / damps) * (ps_norm / chi_n - 1.0)).exp();
+
+        // The decomposition is only refreshed when C changed enough, O(n^3)
+        // would otherwise dominate on large problems
+        if (state.generation - last_decomposition) as f64 * (c1 + cmu) * nf * 10.0 > 1.0 {
+            last_decomposition = state.generation;
+            let (values, vectors) = eigen(&c);
+            d = values.iter().map(|v| v.max(1e-20).sqrt()).collect();
+            b = vectors;
+        }
+    }
+}
+
+fn identity(n: usize) -> Vec<Vec<f64>> {
+    (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect()
+}
+
+fn mat_vec(m: &[Vec<f64>], v: &[f64]) -> Vec<f64> {
+    m.iter().map(|row| row.iter().zip(v).map(|(a, b)| a * b).sum()).collect()
+}
+
+fn mat_t_vec(m: &[Vec<f64>], v: &[f64]) -> Vec<f64> {
+    (0..v.len()).map(|j| m.iter().zip(v).map(|(row, b)| row[j] * b).sum()).collect()
+}
+
+// Cyclic Jacobi eigenvalue algorithm for symmetric matrices, returns the
+// eigenvalues and the matrix having the eigenvectors as columns
+fn eigen(matrix: &[Vec<f64>]) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = matrix.len();
+    let mut a = matrix.to_vec();
+    let mut v = identity(n);
+
+    for _ in 0..64 {
+        let off: f64 = (0..n).flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum();
+        if off < 1e-22 {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let t = if theta == 0.0 { 1.0 } else { t };
+                let (cos, sin) = (1.0 / (t * t + 1.0).sqrt(), t / (t * t + 1.0).sqrt());
+
+                for row in a.iter_mut() {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = cos * kp - sin * kq;
+                    row[q] = sin * kp + cos * kq;
+                }
+                let (top, bottom) = a.split_at_mut(q);
+                for (pk, qk) in top[p].iter_mut().zip(bottom[0].iter_mut()) {
+                    let (x, y) = (*pk, *qk);
+                    *pk = cos * x - sin * y;
+                    *qk = sin * x + cos * y;
+                }
+                for row in v.iter_mut() {
+                    let (vp, vq) = (row[p], row[q]);
+                    row[p] = cos * vp - sin * vq;
+                    row[q] = sin * vp + cos * vq;
+                }
+            }
+        }
+    }
+
+    ((0..n).map(|i| a[i][i]).collect(), v)
+}
+
+#[cfg(test)]
+struct Ellipsoid;
+
+#[cfg(test)]
+impl Evaluator<Vec<f64>> for Ellipsoid {
+
+    fn evaluator(&self, pop: &Vec<f64>) -> f32 {
+        -pop.iter().enumerate().map(|(i, x)| 10f64.powi(i as i32) * (x - 1.0).powi(2)).sum::<f64>() as f32
+    }
+}
+
+#[test]
+fn test_eigen() {
+    let m = vec![vec![4.0, 1.0, 0.5], vec![1.0, 3.0, 0.2], vec![0.5, 0.2, 1.0]];
+    let (values, vectors) = eigen(&m);
+
+    for (i, value) in values.iter().enumerate() {
+        let column: Vec<f64> = vectors.iter().map(|row| row[i]).collect();
+        for (mv, v) in mat_vec(&m, &column).iter().zip(&column) {
+            assert!((mv - value * v).abs() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn test_cma_es() {
+    use crate::criterion::{Iterations, Mark};
+
+    let mut stop_crit = Mark{ max_rating: -1e-8 }.or(Iterations::new(2000));
+    let result = cma_es(&Ellipsoid, &mut stop_crit, vec![-2.0; 4], 1.0, default_lambda(4));
+
+    assert!(result.rating >= -1e-8, "{}", result.rating);
+    assert!(result.best.iter().all(|x| (x - 1.0).abs() < 1e-3));
+}
+
+#[test]
+fn test_self_adaptive_es() {
+    use crate::criterion::{Iterations, Mark};
+    use crate::genome::real::{Bounds, Repair, UniformReal};
+    use crate::genome::self_adaptive::{GenomeOnly, LogNormalStepSizes, StepSizeGenerator};
+
+    let bounds = Bounds::uniform(4, -5.0, 5.0, Repair::Clamp);
+    let generator = StepSizeGenerator{ genes: UniformReal{ bounds: bounds.clone() }, initial_sigma: 1.0, per_gene: true };
+    let mutation = LogNormalStepSizes{ bounds, min_sigma: 1e-12 };
+
+    for selection in [Selection::Comma, Selection::Plus] {
+        let mut stop_crit = Mark{ max_rating: -1e-4 }.or(Iterations::new(3000));
+        let result = evolution_strategy(&generator, &GenomeOnly(Ellipsoid), &Intermediate{ rho: 3 }, &mutation,
+            &mut stop_crit, 10, 60, selection);
+
+        assert!(result.rating >= -1e-4, "{selection:?}: {}", result.rating);
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::evaluator::Evaluator;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Symbol {
+    Terminal(String),
+    // Index of the rule in the grammar
+    NonTerminal(usize)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    pub alternatives: Vec<Vec<Symbol>>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrammarError {
+    // 0 when the error is not tied to a line
+    pub line: usize,
+    pub message: String
+}
+
+impl Display for GrammarError {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for GrammarError {}
+
+// BNF grammar, the first rule being the start symbol:
+//
+//   <expr> ::= <expr> <op> <expr> | (<expr>) | <var>
+//   <op>   ::= + | - | "*"
+//          |   /
+//   <var>  ::= x | y
+//
+// Text outside <...> is kept as is (inner spaces included), quotes allow
+// leading or trailing spaces and literal `|` or `<`. Lines starting with `|`
+// continue the previous rule and lines starting with `#` are comments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grammar {
+    pub rules: Vec<Rule>
+}
+
+impl Grammar {
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, GrammarError> {
+        let text = std::fs::read_to_string(path).map_err(|e| GrammarError {
+            line: 0,
+            message: e.to_string()
+        })?;
+
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, GrammarError> {
+        // Rule name, alternatives as raw text and line of definition
+        let mut raw: Vec<(String, Vec<String>, usize)> = vec![];
+
+        for (n, line) in text.lines().enumerate() {
+            let line_no = n + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix('|') {
+                let Some(last) = raw.last_mut() else {
+                    return Err(GrammarError{ line: line_no, message: String::from("alternative without a rule") });
+                };
+                last.1.extend(split_alternatives(rest));
+            } else {
+                let Some((name, rest)) = line.split_once("::=") else {
+                    return Err(GrammarError{ line: line_no, message: String::from("expected `<rule> ::= ...`") });
+                };
+                let name = name.trim();
+                if !(name.starts_with('<') && name.ends_with('>')) || name.len() < 3 {
+                    return Err(GrammarError{ line: line_no, message: format!("invalid rule name `{name}`") });
+                }
+                if let Some((_, _, first)) = raw.iter().find(|r| r.0 == name[1..name.len() - 1]) {
+                    return Err(GrammarError{ line: line_no, message: format!("rule `{name}` already defined line {first}") });
+                }
+                raw.push((name[1..name.len() - 1].to_string(), split_alternatives(rest), line_no));
+            }
+        }
+
+        if raw.is_empty() {
+            return Err(GrammarError{ line: 0, message: String::from("empty grammar") });
+        }
+
+        let indices: HashMap<&str, usize> = raw.iter().enumerate().map(|(i, r)| (r.0.as_str(), i)).collect();
+        let mut rules = Vec::with_capacity(raw.len());
+        for (name, alternatives, line) in &raw {
+            let alternatives = alternatives.iter()
+                .map(|alt| parse_symbols(alt, &indices, *line))
+                .collect::<Result<Vec<_>, _>>()?;
+            rules.push(Rule { name: name.clone(), alternatives });
+        }
+
+        Ok(Self { rules })
+    }
+
+    // Leftmost derivation from the start rule, each choice between several
+    // alternatives uses the next codon modulo their count. Reading past the
+    // last codon starts over at most `max_wraps` times, None if the
+    // derivation is still not complete by then.
+    pub fn map(&self, codons: &[i64], max_wraps: usize) -> Option<String> {
+        let mut phenotype = String::new();
+        let mut stack = vec![Symbol::NonTerminal(0)];
+        let (mut next, mut wraps) = (0, 0);
+        // Rules with a single alternative don't read codons, this bounds
+        // grammars looping on them
+        let mut expansions = 0;
+        let max_expansions = (codons.len().max(1) * (max_wraps + 1)).max(1) * 64;
+
+        while let Some(symbol) = stack.pop() {
+            let rule = match symbol {
+                Symbol::Terminal(text) => {
+                    phenotype.push_str(&text);
+                    continue;
+                },
+                Symbol::NonTerminal(rule) => &self.rules[rule]
+            };
+
+            expansions += 1;
+            if expansions > max_expansions {
+                return None;
+            }
+
+            let choice = if rule.alternatives.len() == 1 {
+                0
+            } else {
+                if next == codons.len() {
+                    if codons.is_empty() || wraps == max_wraps {
+                        return None;
+                    }
+                    next = 0;
+                    wraps += 1;
+                }
+                next += 1;
+                codons[next - 1].rem_euclid(rule.alternatives.len() as i64) as usize
+            };
+
+            stack.extend(rule.alternatives[choice].iter().rev().cloned());
+        }
+
+        Some(phenotype)
+    }
+}
+
+fn split_alternatives(text: &str) -> Vec<String> {
+    let mut alternatives = vec![];
+    let mut current = String::new();
+    let mut quote = None;
+
+    for c in text.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => { quote = Some(c); current.push(c); },
+            (c, Some(q)) if c == q => { quote = None; current.push(c); },
+            ('|', None) => alternatives.push(std::mem::take(&mut current)),
+            _ => current.push(c)
+        }
+    }
+    alternatives.push(current);
+
+    alternatives.into_iter()
+        .map(|a| a.trim().to_string())
+        .filter(|a| !a.is_empty())
+        .collect()
+}
+
+fn parse_symbols(text: &str, indices: &HashMap<&str, usize>, line: usize) -> Result<Vec<Symbol>, GrammarError> {
+    let mut symbols = vec![];
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '<' => {
+                let name: String = chars.by_ref().take_while(|c| *c != '>').collect();
+                let Some(rule) = indices.get(name.as_str()) else {
+                    return Err(GrammarError{ line, message: format!("undefined rule <{name}>") });
+                };
+                if !literal.is_empty() {
+                    symbols.push(Symbol::Terminal(std::mem::take(&mut literal)));
+                }
+                symbols.push(Symbol::NonTerminal(*rule));
+            },
+            '"' | '\'' => {
+                let quoted: String = chars.by_ref().take_while(|q| *q != c).collect();
+                literal.push_str(&quoted);
+            },
+            _ => literal.push(c)
+        }
+    }
+    if !literal.is_empty() {
+        symbols.push(Symbol::Terminal(literal));
+    }
+
+    Ok(symbols)
+}
+
+// Maps codon genomes through the grammar and rates the phenotype with
+// `evaluator`, genomes failing to map get `invalid_rating`
+pub struct GrammaticalEvaluator<E> {
+    pub grammar: Grammar,
+    pub max_wraps: usize,
+    pub evaluator: E,
+    pub invalid_rating: f32
+}
+
+impl<E: Evaluator<String>> Evaluator<Vec<i64>> for GrammaticalEvaluator<E> {
+
+    fn evaluator(&self, pop: &Vec<i64>) -> f32 {
+        match self.grammar.map(pop, self.max_wraps) {
+            Some(phenotype) => self.evaluator.evaluator(&phenotype),
+            None => self.invalid_rating
+        }
+    }
+}
+
+#[cfg(test)]
+const EXPR: &str = "
+# Arithmetic expressions
+<expr> ::= <expr><op><expr> | (<expr>) | <var>
+<op>   ::= \" + \" | \" - \"
+       |   \" * \"
+<var>  ::= x | y
+";
+
+#[test]
+fn test_parse() {
+    let grammar = Grammar::parse(EXPR).unwrap();
+
+    assert_eq!(grammar.rules.len(), 3);
+    assert_eq!(grammar.rules[1].alternatives.len(), 3);
+    assert_eq!(grammar.rules[1].alternatives[2], vec![Symbol::Terminal(String::from(" * "))]);
+    assert_eq!(grammar.rules[0].alternatives[1], vec![
+        Symbol::Terminal(String::from("(")), Symbol::NonTerminal(0), Symbol::Terminal(String::from(")"))
+    ]);
+
+    let err = Grammar::parse("<a> ::= <b>").unwrap_err();
+    assert_eq!(err.line, 1);
+
+    let err = Grammar::parse("<a> ::= x\n<b> ::= y\n<a> ::= z").unwrap_err();
+    assert_eq!(err.line, 3);
+}
+
+#[test]
+fn test_map() {
+    let grammar = Grammar::parse(EXPR).unwrap();
+
+    // expr -> expr op expr, expr -> var -> x, op -> " * ", expr -> var -> y
+    assert_eq!(grammar.map(&[0, 2, 0, 2, 2, 1], 0), Some(String::from("x * y")));
+    // Same derivation reading the codons twice
+    assert_eq!(grammar.map(&[0, 2, 0], 1), Some(String::from("x + x")));
+    assert_eq!(grammar.map(&[0, 2, 0], 0), None);
+    assert_eq!(grammar.map(&[0], 10), None);
+}
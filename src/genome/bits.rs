@@ -0,0 +1,357 @@
+use std::fmt::Display;
+
+use rand::{thread_rng, Rng, seq::index::sample};
+
+use crate::crossover::Crossover;
+use crate::evaluator::Evaluator;
+use crate::generator::Generator;
+use crate::mating::Hamming;
+use crate::mutation::Mutation;
+
+const WORD: usize = u64::BITS as usize;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitGenome {
+    words: Vec<u64>,
+    len: usize
+}
+
+impl BitGenome {
+
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(WORD)],
+            len
+        }
+    }
+
+    pub fn random(len: usize) -> Self {
+        let mut rng = thread_rng();
+        let mut genome = Self {
+            words: (0..len.div_ceil(WORD)).map(|_| rng.gen()).collect(),
+            len
+        };
+        genome.clear_tail();
+
+        genome
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        assert!(i < self.len);
+        self.words[i / WORD] >> (i % WORD) & 1 == 1
+    }
+
+    pub fn set(&mut self, i: usize, value: bool) {
+        assert!(i < self.len);
+        if value {
+            self.words[i / WORD] |= 1 << (i % WORD);
+        } else {
+            self.words[i / WORD] &= !(1 << (i % WORD));
+        }
+    }
+
+    pub fn flip(&mut self, i: usize) {
+        assert!(i < self.len);
+        self.words[i / WORD] ^= 1 << (i % WORD);
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|i| self.get(i))
+    }
+
+    // Bits past `len` in the last word must stay at 0 for count_ones and
+    // hamming to be right
+    fn clear_tail(&mut self) {
+        if !self.len.is_multiple_of(WORD) {
+            let last = self.words.len() - 1;
+            self.words[last] &= (1 << (self.len % WORD)) - 1;
+        }
+    }
+}
+
+impl From<&[bool]> for BitGenome {
+
+    fn from(bits: &[bool]) -> Self {
+        let mut genome = BitGenome::new(bits.len());
+        for (i, b) in bits.iter().enumerate() {
+            genome.set(i, *b);
+        }
+
+        genome
+    }
+}
+
+impl Display for BitGenome {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for b in self.iter() {
+            write!(f, "{}", if b { '1' } else { '0' })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Hamming for BitGenome {
+
+    // Bits past the shorter genome all count as different
+    fn hamming(&self, other: &Self) -> usize {
+        let shared = self.len.min(other.len);
+        let diff: usize = self.words.iter().zip(other.words.iter())
+            .take(shared.div_ceil(WORD))
+            .enumerate()
+            .map(|(i, (a, b))| {
+                let bits = (shared - i * WORD).min(WORD);
+                let mask = if bits == WORD { u64::MAX } else { (1 << bits) - 1 };
+                ((a ^ b) & mask).count_ones() as usize
+            })
+            .sum();
+
+        diff + self.len.abs_diff(other.len)
+    }
+}
+
+pub struct RandomBits {
+    pub len: usize
+}
+
+impl Generator<BitGenome> for RandomBits {
+
+    fn generator(&self) -> BitGenome {
+        BitGenome::random(self.len)
+    }
+}
+
+// Swaps every other segment between the cut points
+fn k_point(parent1: &BitGenome, parent2: &BitGenome, points: usize) -> (BitGenome, BitGenome) {
+    let mut rng = thread_rng();
+    let len = parent1.len.min(parent2.len);
+    let range = len.saturating_sub(1);
+    let mut cuts: Vec<usize> = sample(&mut rng, range, points.min(range))
+        .into_iter()
+        .map(|c| c + 1)
+        .collect();
+    cuts.sort_unstable();
+
+    let (mut child1, mut child2) = (parent1.clone(), parent2.clone());
+    let mut swap = false;
+    let mut cuts = cuts.into_iter().peekable();
+    for i in 0..len {
+        while cuts.next_if(|c| *c <= i).is_some() {
+            swap = !swap;
+        }
+        if swap {
+            child1.set(i, parent2.get(i));
+            child2.set(i, parent1.get(i));
+        }
+    }
+
+    (child1, child2)
+}
+
+pub struct OnePoint;
+
+impl Crossover<BitGenome> for OnePoint
+{
+    fn crossover(&self, parent1: &BitGenome, parent2: &BitGenome) -> BitGenome {
+        k_point(parent1, parent2, 1).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &BitGenome, parent2: &BitGenome) -> (BitGenome, BitGenome) {
+        k_point(parent1, parent2, 1)
+    }
+}
+
+pub struct TwoPoint;
+
+impl Crossover<BitGenome> for TwoPoint
+{
+    fn crossover(&self, parent1: &BitGenome, parent2: &BitGenome) -> BitGenome {
+        k_point(parent1, parent2, 2).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &BitGenome, parent2: &BitGenome) -> (BitGenome, BitGenome) {
+        k_point(parent1, parent2, 2)
+    }
+}
+
+pub struct KPoint {
+    pub points: usize
+}
+
+impl Crossover<BitGenome> for KPoint
+{
+    fn crossover(&self, parent1: &BitGenome, parent2: &BitGenome) -> BitGenome {
+        k_point(parent1, parent2, self.points).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &BitGenome, parent2: &BitGenome) -> (BitGenome, BitGenome) {
+        k_point(parent1, parent2, self.points)
+    }
+}
+
+// Each bit comes from the second parent with probability `rate`
+pub struct Uniform {
+    pub rate: f64
+}
+
+impl Crossover<BitGenome> for Uniform
+{
+    fn crossover(&self, parent1: &BitGenome, parent2: &BitGenome) -> BitGenome {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &BitGenome, parent2: &BitGenome) -> (BitGenome, BitGenome) {
+        let mut rng = thread_rng();
+        let (mut child1, mut child2) = (parent1.clone(), parent2.clone());
+
+        for i in 0..parent1.len.min(parent2.len) {
+            if rng.gen_bool(self.rate) {
+                child1.set(i, parent2.get(i));
+                child2.set(i, parent1.get(i));
+            }
+        }
+
+        (child1, child2)
+    }
+}
+
+// Each bit flips with probability `rate`
+pub struct BitFlip {
+    pub rate: f64
+}
+
+impl Mutation<BitGenome> for BitFlip
+{
+    fn mutation(&self, pop: &mut BitGenome) {
+        let mut rng = thread_rng();
+
+        for i in 0..pop.len {
+            if rng.gen_bool(self.rate) {
+                pop.flip(i);
+            }
+        }
+    }
+}
+
+pub struct OneMax;
+
+impl Evaluator<BitGenome> for OneMax {
+
+    fn evaluator(&self, pop: &BitGenome) -> f32 {
+        pop.count_ones() as f32
+    }
+}
+
+// Each block made only of ones is worth `block_size`, the others nothing.
+// Trailing bits that do not fill a block are ignored, `block_size` can't be 0.
+pub struct RoyalRoad {
+    pub block_size: usize
+}
+
+impl Evaluator<BitGenome> for RoyalRoad {
+
+    fn evaluator(&self, pop: &BitGenome) -> f32 {
+        assert!(self.block_size > 0, "royal road blocks need at least one bit");
+        let full = (0..pop.len / self.block_size)
+            .filter(|b| (b * self.block_size..(b + 1) * self.block_size).all(|i| pop.get(i)))
+            .count();
+
+        (full * self.block_size) as f32
+    }
+}
+
+// Deceptive trap: a block with u ones is worth `block_size` if all are set,
+// `block_size - 1 - u` otherwise, so the gradient leads towards zeros.
+// Same blocks as `RoyalRoad`.
+pub struct Trap {
+    pub block_size: usize
+}
+
+impl Evaluator<BitGenome> for Trap {
+
+    fn evaluator(&self, pop: &BitGenome) -> f32 {
+        let k = self.block_size;
+        assert!(k > 0, "trap blocks need at least one bit");
+
+        (0..pop.len / k)
+            .map(|b| (b * k..(b + 1) * k).filter(|i| pop.get(*i)).count())
+            .map(|u| if u == k { k } else { k - 1 - u })
+            .sum::<usize>() as f32
+    }
+}
+
+#[test]
+fn test_k_point_keeps_genes() {
+    let parent1 = BitGenome::new(130);
+    let mut parent2 = BitGenome::new(130);
+    for i in 0..130 {
+        parent2.set(i, true);
+    }
+
+    for points in 1..5 {
+        let (child1, child2) = KPoint{ points }.crossover_pair(&parent1, &parent2);
+        assert_eq!(child1.count_ones() + child2.count_ones(), 130);
+        assert_eq!(child1.hamming(&child2), 130);
+    }
+    let children = crate::crossover::MultiCrossover::multi_crossover(&KPoint{ points: 2 }, &[&parent1, &parent2]);
+    assert_eq!(children.len(), 2);
+}
+
+#[test]
+fn test_hamming() {
+    let short = BitGenome::from(&[true, false, true][..]);
+    let mut long = BitGenome::new(70);
+    long.set(0, true);
+    long.set(40, true);
+
+    // Bit 2 differs, the 67 bits past the short genome count once
+    assert_eq!(short.hamming(&long), 1 + 67);
+    assert_eq!(long.hamming(&short), 1 + 67);
+    assert_eq!(long.hamming(&long), 0);
+}
+
+#[test]
+fn test_benchmarks() {
+    let genome = BitGenome::from(&[true, true, true, false, false, false, true, true][..]);
+
+    assert_eq!(genome.to_string(), "11100011");
+    assert_eq!(OneMax.evaluator(&genome), 5.0);
+    assert_eq!(RoyalRoad{ block_size: 4 }.evaluator(&genome), 0.0);
+    assert_eq!(RoyalRoad{ block_size: 2 }.evaluator(&genome), 4.0);
+    assert_eq!(Trap{ block_size: 4 }.evaluator(&genome), 0.0 + 1.0);
+}
+
+#[test]
+#[should_panic(expected = "at least one bit")]
+fn test_empty_blocks() {
+    RoyalRoad{ block_size: 0 }.evaluator(&BitGenome::new(8));
+}
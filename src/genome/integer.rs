@@ -0,0 +1,153 @@
+use rand::{thread_rng, Rng};
+
+use crate::crossover::Crossover;
+use crate::generator::Generator;
+use crate::genome::real::standard_normal;
+use crate::mutation::Mutation;
+
+// Vec<i64> genomes with every gene in min..=max
+pub struct RandomInts {
+    pub len: usize,
+    pub min: i64,
+    pub max: i64
+}
+
+impl Generator<Vec<i64>> for RandomInts {
+
+    fn generator(&self) -> Vec<i64> {
+        let mut rng = thread_rng();
+
+        (0..self.len).map(|_| rng.gen_range(self.min..=self.max)).collect()
+    }
+}
+
+pub struct OnePoint;
+
+impl Crossover<Vec<i64>> for OnePoint
+{
+    fn crossover(&self, parent1: &Vec<i64>, parent2: &Vec<i64>) -> Vec<i64> {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &Vec<i64>, parent2: &Vec<i64>) -> (Vec<i64>, Vec<i64>) {
+        let len = parent1.len().min(parent2.len());
+        let cut = thread_rng().gen_range(0..=len);
+        let (mut child1, mut child2) = (parent1.clone(), parent2.clone());
+
+        child1[cut..len].copy_from_slice(&parent2[cut..len]);
+        child2[cut..len].copy_from_slice(&parent1[cut..len]);
+
+        (child1, child2)
+    }
+}
+
+// Each gene comes from the second parent with probability `rate`
+pub struct Uniform {
+    pub rate: f64
+}
+
+impl Crossover<Vec<i64>> for Uniform
+{
+    fn crossover(&self, parent1: &Vec<i64>, parent2: &Vec<i64>) -> Vec<i64> {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &Vec<i64>, parent2: &Vec<i64>) -> (Vec<i64>, Vec<i64>) {
+        let mut rng = thread_rng();
+        let (mut child1, mut child2) = (parent1.clone(), parent2.clone());
+
+        for i in 0..parent1.len().min(parent2.len()) {
+            if rng.gen_bool(self.rate) {
+                child1[i] = parent2[i];
+                child2[i] = parent1[i];
+            }
+        }
+
+        (child1, child2)
+    }
+}
+
+// Each gene is redrawn in min..=max with probability `rate`
+pub struct RandomReset {
+    pub rate: f64,
+    pub min: i64,
+    pub max: i64
+}
+
+impl Mutation<Vec<i64>> for RandomReset
+{
+    fn mutation(&self, pop: &mut Vec<i64>) {
+        let mut rng = thread_rng();
+
+        for x in pop.iter_mut() {
+            if rng.gen_bool(self.rate) {
+                *x = rng.gen_range(self.min..=self.max);
+            }
+        }
+    }
+}
+
+// Each gene moves by a rounded gaussian step of deviation `sigma` with
+// probability `rate`, staying in min..=max
+pub struct Creep {
+    pub rate: f64,
+    pub sigma: f64,
+    pub min: i64,
+    pub max: i64
+}
+
+impl Mutation<Vec<i64>> for Creep
+{
+    fn mutation(&self, pop: &mut Vec<i64>) {
+        let mut rng = thread_rng();
+
+        for x in pop.iter_mut() {
+            if rng.gen_bool(self.rate) {
+                let step = (standard_normal(&mut rng) * self.sigma).round() as i64;
+                *x = x.saturating_add(step).clamp(self.min, self.max);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_mutations_stay_in_bounds() {
+    let reset = RandomReset{ rate: 1.0, min: -2, max: 2 };
+    let creep = Creep{ rate: 1.0, sigma: 10.0, min: 0, max: 5 };
+    let widest = Creep{ rate: 1.0, sigma: 1e6, min: i64::MIN, max: i64::MAX };
+
+    for _ in 0..100 {
+        let mut genome = RandomInts{ len: 10, min: 0, max: 5 }.generator();
+        creep.mutation(&mut genome);
+        assert!(genome.iter().all(|x| (0..=5).contains(x)), "{genome:?}");
+        reset.mutation(&mut genome);
+        assert!(genome.iter().all(|x| (-2..=2).contains(x)), "{genome:?}");
+
+        let mut genome = vec![i64::MIN, i64::MAX];
+        widest.mutation(&mut genome);
+    }
+
+    // Steps are rounded, a sigma of a few units still moves genes
+    let mut genome = vec![0; 20];
+    Creep{ rate: 1.0, sigma: 5.0, min: -100, max: 100 }.mutation(&mut genome);
+    assert!(genome.iter().any(|x| *x != 0));
+}
+
+#[test]
+fn test_crossovers_keep_genes() {
+    let (parent1, parent2) = (vec![0; 8], vec![1; 8]);
+
+    let pairs = [OnePoint.crossover_pair(&parent1, &parent2), Uniform{ rate: 0.5 }.crossover_pair(&parent1, &parent2)];
+    for (child1, child2) in pairs {
+        assert!(child1.iter().zip(&child2).all(|(a, b)| a + b == 1));
+    }
+    assert_eq!(Uniform{ rate: 1.0 }.crossover_pair(&parent1, &parent2), (parent2.clone(), parent1.clone()));
+}
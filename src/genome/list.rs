@@ -0,0 +1,141 @@
+use rand::{thread_rng, Rng};
+
+use crate::crossover::Crossover;
+use crate::generator::Generator;
+use crate::mutation::Mutation;
+
+// Variable length Vec<E> genomes, every length staying in min_len..=max_len.
+// New genes come from a `Generator<E>`.
+pub struct RandomList<G> {
+    pub min_len: usize,
+    pub max_len: usize,
+    pub gene: G
+}
+
+impl<E, G: Generator<E>> Generator<Vec<E>> for RandomList<G> {
+
+    fn generator(&self) -> Vec<E> {
+        let len = thread_rng().gen_range(self.min_len..=self.max_len.max(self.min_len));
+
+        (0..len).map(|_| self.gene.generator()).collect()
+    }
+}
+
+// Each parent is cut at its own point and the tails are swapped, so children
+// lengths differ from their parents'. Cuts giving a child out of the length
+// bounds are drawn again, the parents are returned if none fits.
+pub struct CutAndSplice {
+    pub min_len: usize,
+    pub max_len: usize
+}
+
+const CUT_ATTEMPTS: usize = 16;
+
+impl<E: Clone> Crossover<Vec<E>> for CutAndSplice
+{
+    fn crossover(&self, parent1: &Vec<E>, parent2: &Vec<E>) -> Vec<E> {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &Vec<E>, parent2: &Vec<E>) -> (Vec<E>, Vec<E>) {
+        let mut rng = thread_rng();
+        let bounds = self.min_len..=self.max_len;
+
+        for _ in 0..CUT_ATTEMPTS {
+            let (cut1, cut2) = (rng.gen_range(0..=parent1.len()), rng.gen_range(0..=parent2.len()));
+            let len1 = cut1 + parent2.len() - cut2;
+            let len2 = cut2 + parent1.len() - cut1;
+
+            if bounds.contains(&len1) && bounds.contains(&len2) {
+                let child1 = parent1[..cut1].iter().chain(parent2[cut2..].iter()).cloned().collect();
+                let child2 = parent2[..cut2].iter().chain(parent1[cut1..].iter()).cloned().collect();
+                return (child1, child2);
+            }
+        }
+
+        (parent1.clone(), parent2.clone())
+    }
+}
+
+// Inserts a new gene at a random position, unless the genome is at max_len
+pub struct Insertion<G> {
+    pub max_len: usize,
+    pub gene: G
+}
+
+impl<E, G: Generator<E>> Mutation<Vec<E>> for Insertion<G>
+{
+    fn mutation(&self, pop: &mut Vec<E>) {
+        if pop.len() < self.max_len {
+            let pos = thread_rng().gen_range(0..=pop.len());
+            pop.insert(pos, self.gene.generator());
+        }
+    }
+}
+
+// Removes a random gene, unless the genome is at min_len
+pub struct Deletion {
+    pub min_len: usize
+}
+
+impl<E> Mutation<Vec<E>> for Deletion
+{
+    fn mutation(&self, pop: &mut Vec<E>) {
+        if pop.len() > self.min_len && !pop.is_empty() {
+            let pos = thread_rng().gen_range(0..pop.len());
+            pop.remove(pos);
+        }
+    }
+}
+
+// Replaces each gene by a new one with probability `rate`
+pub struct Replace<G> {
+    pub rate: f64,
+    pub gene: G
+}
+
+impl<E, G: Generator<E>> Mutation<Vec<E>> for Replace<G>
+{
+    fn mutation(&self, pop: &mut Vec<E>) {
+        let mut rng = thread_rng();
+
+        for x in pop.iter_mut() {
+            if rng.gen_bool(self.rate) {
+                *x = self.gene.generator();
+            }
+        }
+    }
+}
+
+#[test]
+fn test_length_bounds() {
+    struct Zero;
+
+    impl Generator<u8> for Zero {
+
+        fn generator(&self) -> u8 {
+            0
+        }
+    }
+
+    let generator = RandomList{ min_len: 2, max_len: 6, gene: Zero };
+    let crossover = CutAndSplice{ min_len: 2, max_len: 6 };
+    let insertion = Insertion{ max_len: 6, gene: Zero };
+    let deletion = Deletion{ min_len: 2 };
+
+    for _ in 0..200 {
+        let (parent1, parent2) = (generator.generator(), generator.generator());
+        let (mut child1, mut child2) = crossover.crossover_pair(&parent1, &parent2);
+        assert_eq!(child1.len() + child2.len(), parent1.len() + parent2.len());
+
+        insertion.mutation(&mut child1);
+        deletion.mutation(&mut child2);
+        for child in [child1, child2] {
+            assert!((2..=6).contains(&child.len()));
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use rand::{thread_rng, Rng};
+
+use crate::crossover::Crossover;
+use crate::generator::Generator;
+use crate::genome::real::standard_normal;
+use crate::mutation::Mutation;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Domain {
+    Bool,
+    // Inclusive bounds
+    Int { min: i64, max: i64 },
+    Categorical(Vec<String>),
+    Float { min: f64, max: f64 }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gene {
+    Bool(bool),
+    Int(i64),
+    // Index in the labels of the domain
+    Category(usize),
+    Float(f64)
+}
+
+impl Gene {
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self { Gene::Bool(b) => Some(*b), _ => None }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self { Gene::Int(i) => Some(*i), _ => None }
+    }
+
+    pub fn as_category(&self) -> Option<usize> {
+        match self { Gene::Category(c) => Some(*c), _ => None }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self { Gene::Float(f) => Some(*f), _ => None }
+    }
+}
+
+// Domain of each gene of the chromosome, in order
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    pub domains: Vec<Domain>
+}
+
+impl Schema {
+
+    pub fn new(domains: Vec<Domain>) -> Self {
+        Self {
+            domains
+        }
+    }
+
+    pub fn random(&self) -> Vec<Gene> {
+        let mut rng = thread_rng();
+
+        self.domains.iter().map(|d| match d {
+            Domain::Bool => Gene::Bool(rng.gen()),
+            Domain::Int { min, max } => Gene::Int(rng.gen_range(*min..=*max)),
+            Domain::Categorical(labels) => Gene::Category(rng.gen_range(0..labels.len())),
+            Domain::Float { min, max } => Gene::Float(min + rng.gen::<f64>() * (max - min))
+        }).collect()
+    }
+
+    // Whether every gene has the type and range of its domain
+    pub fn is_valid(&self, genome: &[Gene]) -> bool {
+        genome.len() == self.domains.len() && genome.iter().zip(self.domains.iter()).all(|(g, d)| {
+            match (g, d) {
+                (Gene::Bool(_), Domain::Bool) => true,
+                (Gene::Int(i), Domain::Int { min, max }) => (min..=max).contains(&i),
+                (Gene::Category(c), Domain::Categorical(labels)) => *c < labels.len(),
+                (Gene::Float(f), Domain::Float { min, max }) => (min..=max).contains(&f),
+                _ => false
+            }
+        })
+    }
+
+    // Label of a categorical gene
+    pub fn label(&self, i: usize, gene: &Gene) -> Option<&str> {
+        match (&self.domains[i], gene) {
+            (Domain::Categorical(labels), Gene::Category(c)) => labels.get(*c).map(|l| l.as_str()),
+            _ => None
+        }
+    }
+}
+
+pub struct RandomMixed {
+    pub schema: Schema
+}
+
+impl Generator<Vec<Gene>> for RandomMixed {
+
+    fn generator(&self) -> Vec<Gene> {
+        self.schema.random()
+    }
+}
+
+// Booleans and categories are swapped between parents with probability
+// `rate`, numbers are blended at a random point between both parents and kept
+// in their domain. Genes that don't match their domain are left as is, as
+// are those past the shorter of the parents and the schema.
+pub struct MixedCrossover {
+    pub schema: Schema,
+    pub rate: f64
+}
+
+impl Crossover<Vec<Gene>> for MixedCrossover
+{
+    fn crossover(&self, parent1: &Vec<Gene>, parent2: &Vec<Gene>) -> Vec<Gene> {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &Vec<Gene>, parent2: &Vec<Gene>) -> (Vec<Gene>, Vec<Gene>) {
+        let mut rng = thread_rng();
+        let (mut child1, mut child2) = (parent1.clone(), parent2.clone());
+
+        let genes = parent1.iter().zip(parent2.iter()).zip(self.schema.domains.iter());
+        for (i, ((a, b), domain)) in genes.enumerate() {
+            match (*a, *b, domain) {
+                (Gene::Int(a), Gene::Int(b), Domain::Int { min, max }) => {
+                    let t: f64 = rng.gen();
+                    // In i128 as b - a overflows on the widest domains
+                    let diff = (b as i128 - a as i128) as f64;
+                    child1[i] = Gene::Int(((a as f64 + t * diff).round() as i64).clamp(*min, *max));
+                    child2[i] = Gene::Int(((b as f64 - t * diff).round() as i64).clamp(*min, *max));
+                },
+                (Gene::Float(a), Gene::Float(b), Domain::Float { min, max }) => {
+                    let t: f64 = rng.gen();
+                    child1[i] = Gene::Float((a + t * (b - a)).clamp(*min, *max));
+                    child2[i] = Gene::Float((b + t * (a - b)).clamp(*min, *max));
+                },
+                (a @ Gene::Bool(_), b @ Gene::Bool(_), Domain::Bool)
+                | (a @ Gene::Category(_), b @ Gene::Category(_), Domain::Categorical(_)) if rng.gen_bool(self.rate) => {
+                    child1[i] = b;
+                    child2[i] = a;
+                },
+                _ => {}
+            }
+        }
+
+        (child1, child2)
+    }
+}
+
+// Each gene mutates with probability `rate`: booleans flip, categories change
+// to another label, numbers take a gaussian step of `step` times the width of
+// their domain
+pub struct MixedMutation {
+    pub schema: Schema,
+    pub rate: f64,
+    pub step: f64
+}
+
+impl Mutation<Vec<Gene>> for MixedMutation
+{
+    fn mutation(&self, pop: &mut Vec<Gene>) {
+        let mut rng = thread_rng();
+
+        for (gene, domain) in pop.iter_mut().zip(self.schema.domains.iter()) {
+            if !rng.gen_bool(self.rate) {
+                continue;
+            }
+
+            *gene = match (*gene, domain) {
+                (Gene::Bool(b), Domain::Bool) => Gene::Bool(!b),
+                (Gene::Int(i), Domain::Int { min, max }) => {
+                    let step = standard_normal(&mut rng) * self.step * (*max as i128 - *min as i128) as f64;
+                    // At least one unit so narrow domains still move
+                    let step = if step.abs() < 1.0 { step.signum() } else { step.round() };
+                    Gene::Int(i.saturating_add(step as i64).clamp(*min, *max))
+                },
+                (Gene::Category(c), Domain::Categorical(labels)) if labels.len() > 1 => {
+                    let other = rng.gen_range(0..labels.len() - 1);
+                    Gene::Category(if other >= c { other + 1 } else { other })
+                },
+                (Gene::Float(f), Domain::Float { min, max }) => {
+                    let step = standard_normal(&mut rng) * self.step * (max - min);
+                    Gene::Float((f + step).clamp(*min, *max))
+                },
+                (gene, _) => gene
+            };
+        }
+    }
+}
+
+#[test]
+fn test_operators_respect_schema() {
+    let schema = Schema::new(vec![
+        Domain::Bool,
+        Domain::Int { min: -3, max: 3 },
+        Domain::Categorical(vec![String::from("relu"), String::from("tanh"), String::from("sigmoid")]),
+        Domain::Float { min: 0.0, max: 1.0 },
+        Domain::Int { min: 0, max: 0 }
+    ]);
+    let crossover = MixedCrossover{ schema: schema.clone(), rate: 0.5 };
+    let mutation = MixedMutation{ schema: schema.clone(), rate: 1.0, step: 0.5 };
+
+    for _ in 0..200 {
+        let (parent1, parent2) = (schema.random(), schema.random());
+        let (mut child1, child2) = crossover.crossover_pair(&parent1, &parent2);
+        mutation.mutation(&mut child1);
+
+        assert!(schema.is_valid(&child1), "{child1:?}");
+        assert!(schema.is_valid(&child2), "{child2:?}");
+        assert!(schema.label(2, &child1[2]).is_some());
+    }
+}
+
+#[test]
+fn test_widest_int_domain() {
+    let schema = Schema::new(vec![Domain::Int { min: i64::MIN, max: i64::MAX }, Domain::Int { min: 0, max: 10 }]);
+    let crossover = MixedCrossover{ schema: schema.clone(), rate: 0.5 };
+    let mutation = MixedMutation{ schema: schema.clone(), rate: 1.0, step: 0.5 };
+
+    let (parent1, parent2) = (vec![Gene::Int(i64::MIN), Gene::Int(0)], vec![Gene::Int(i64::MAX), Gene::Int(10)]);
+    for _ in 0..100 {
+        let (mut child1, child2) = crossover.crossover_pair(&parent1, &parent2);
+        mutation.mutation(&mut child1);
+
+        assert!(schema.is_valid(&child1), "{child1:?}");
+        assert!(schema.is_valid(&child2), "{child2:?}");
+    }
+}
+
+#[test]
+fn test_shorter_than_schema() {
+    let schema = Schema::new(vec![Domain::Int { min: 0, max: 10 }, Domain::Bool, Domain::Bool]);
+    let crossover = MixedCrossover{ schema, rate: 1.0 };
+
+    let (child1, child2) = crossover.crossover_pair(&vec![Gene::Int(0), Gene::Bool(true)], &vec![Gene::Int(10)]);
+    assert_eq!((child1.len(), child2.len()), (2, 1));
+    assert_eq!(child1[1], Gene::Bool(true));
+}
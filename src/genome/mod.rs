@@ -0,0 +1,7 @@
+pub mod bits;
+pub mod real;
+pub mod permutation;
+pub mod integer;
+pub mod mixed;
+pub mod list;
+pub mod self_adaptive;
@@ -0,0 +1,393 @@
+use std::fmt::Display;
+use std::ops::Index;
+
+use rand::{thread_rng, Rng, seq::SliceRandom};
+
+use crate::crossover::Crossover;
+use crate::generator::Generator;
+use crate::mating::Hamming;
+use crate::mutation::Mutation;
+
+// Ordering of 0..len. Every operator below keeps it valid, it can only be
+// built from a vector holding each value exactly once.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Permutation(Vec<usize>);
+
+impl Permutation {
+
+    pub fn identity(len: usize) -> Self {
+        Self((0..len).collect())
+    }
+
+    pub fn random(len: usize) -> Self {
+        let mut perm = Self::identity(len);
+        perm.0.shuffle(&mut thread_rng());
+
+        perm
+    }
+
+    // None if `order` is not a permutation of 0..order.len()
+    pub fn from_vec(order: Vec<usize>) -> Option<Self> {
+        let mut seen = vec![false; order.len()];
+        for v in &order {
+            if *v >= order.len() || seen[*v] {
+                return None;
+            }
+            seen[*v] = true;
+        }
+
+        Some(Self(order))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[usize] {
+        &self.0
+    }
+
+    pub fn into_vec(self) -> Vec<usize> {
+        self.0
+    }
+
+    // positions()[v] is the index of the value v
+    pub fn positions(&self) -> Vec<usize> {
+        let mut pos = vec![0; self.0.len()];
+        for (i, v) in self.0.iter().enumerate() {
+            pos[*v] = i;
+        }
+
+        pos
+    }
+}
+
+impl Index<usize> for Permutation {
+    type Output = usize;
+
+    fn index(&self, i: usize) -> &usize {
+        &self.0[i]
+    }
+}
+
+impl Display for Permutation {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let values: Vec<String> = self.0.iter().map(|v| v.to_string()).collect();
+        write!(f, "[{}]", values.join(" "))
+    }
+}
+
+impl Hamming for Permutation {
+
+    fn hamming(&self, other: &Self) -> usize {
+        self.0.hamming(&other.0)
+    }
+}
+
+pub struct RandomPermutation {
+    pub len: usize
+}
+
+impl Generator<Permutation> for RandomPermutation {
+
+    fn generator(&self) -> Permutation {
+        Permutation::random(self.len)
+    }
+}
+
+// Two sorted cut points in 0..=len
+fn cut_points(len: usize) -> (usize, usize) {
+    let mut rng = thread_rng();
+    let (a, b) = (rng.gen_range(0..=len), rng.gen_range(0..=len));
+
+    (a.min(b), a.max(b))
+}
+
+fn pmx_child(donor: &Permutation, other: &Permutation, a: usize, b: usize) -> Permutation {
+    let donor_pos = donor.positions();
+    let mut child = other.0.clone();
+
+    child[a..b].copy_from_slice(&donor.0[a..b]);
+    for i in (0..a).chain(b..other.len()) {
+        // Follow the mapping of the segment until the value is free
+        let mut v = other.0[i];
+        while (a..b).contains(&donor_pos[v]) {
+            v = other.0[donor_pos[v]];
+        }
+        child[i] = v;
+    }
+
+    Permutation(child)
+}
+
+// Partially mapped crossover
+pub struct Pmx;
+
+impl Crossover<Permutation> for Pmx
+{
+    fn crossover(&self, parent1: &Permutation, parent2: &Permutation) -> Permutation {
+        let (a, b) = cut_points(parent1.len());
+
+        pmx_child(parent1, parent2, a, b)
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &Permutation, parent2: &Permutation) -> (Permutation, Permutation) {
+        let (a, b) = cut_points(parent1.len());
+
+        (pmx_child(parent1, parent2, a, b), pmx_child(parent2, parent1, a, b))
+    }
+}
+
+fn ox_child(donor: &Permutation, other: &Permutation, a: usize, b: usize) -> Permutation {
+    let len = donor.len();
+    let mut used = vec![false; len];
+    for v in &donor.0[a..b] {
+        used[*v] = true;
+    }
+
+    // The other parent's order, starting after the segment, fills the rest
+    let mut fill = (0..len).map(|i| other.0[(b + i) % len]).filter(|v| !used[*v]);
+    let mut child = donor.0.clone();
+    for i in (b..len).chain(0..a) {
+        child[i] = fill.next().unwrap();
+    }
+
+    Permutation(child)
+}
+
+// Order crossover (OX1)
+pub struct Ox1;
+
+impl Crossover<Permutation> for Ox1
+{
+    fn crossover(&self, parent1: &Permutation, parent2: &Permutation) -> Permutation {
+        let (a, b) = cut_points(parent1.len());
+
+        ox_child(parent1, parent2, a, b)
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &Permutation, parent2: &Permutation) -> (Permutation, Permutation) {
+        let (a, b) = cut_points(parent1.len());
+
+        (ox_child(parent1, parent2, a, b), ox_child(parent2, parent1, a, b))
+    }
+}
+
+// Cycle crossover: cycles are copied alternately from each parent
+pub struct Cx;
+
+impl Crossover<Permutation> for Cx
+{
+    fn crossover(&self, parent1: &Permutation, parent2: &Permutation) -> Permutation {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &Permutation, parent2: &Permutation) -> (Permutation, Permutation) {
+        let len = parent1.len();
+        let pos1 = parent1.positions();
+        let (mut child1, mut child2) = (parent1.0.clone(), parent2.0.clone());
+        let mut visited = vec![false; len];
+        let mut swap = false;
+
+        for start in 0..len {
+            if visited[start] {
+                continue;
+            }
+
+            let mut i = start;
+            while !visited[i] {
+                visited[i] = true;
+                if swap {
+                    child1[i] = parent2.0[i];
+                    child2[i] = parent1.0[i];
+                }
+                i = pos1[parent2.0[i]];
+            }
+            swap = !swap;
+        }
+
+        (Permutation(child1), Permutation(child2))
+    }
+}
+
+// Edge recombination: the child keeps the adjacencies of both parents,
+// always moving to the neighbour with the fewest edges left
+pub struct Erx;
+
+impl Crossover<Permutation> for Erx
+{
+    fn crossover(&self, parent1: &Permutation, parent2: &Permutation) -> Permutation {
+        let len = parent1.len();
+        if len == 0 {
+            return parent1.clone();
+        }
+
+        let mut rng = thread_rng();
+        let mut edges: Vec<Vec<usize>> = vec![vec![]; len];
+        for parent in [parent1, parent2] {
+            for i in 0..len {
+                let v = parent.0[i];
+                for n in [parent.0[(i + len - 1) % len], parent.0[(i + 1) % len]] {
+                    if n != v && !edges[v].contains(&n) {
+                        edges[v].push(n);
+                    }
+                }
+            }
+        }
+
+        let mut used = vec![false; len];
+        let mut child = Vec::with_capacity(len);
+        let mut current = if rng.gen_bool(0.5) { parent1.0[0] } else { parent2.0[0] };
+
+        loop {
+            child.push(current);
+            used[current] = true;
+            for e in edges.iter_mut() {
+                e.retain(|n| *n != current);
+            }
+            if child.len() == len {
+                break;
+            }
+
+            let fewest = edges[current].iter().map(|n| edges[*n].len()).min();
+            current = match fewest {
+                Some(fewest) => {
+                    let ties: Vec<usize> = edges[current].iter()
+                        .copied()
+                        .filter(|n| edges[*n].len() == fewest)
+                        .collect();
+                    *ties.choose(&mut rng).unwrap()
+                },
+                None => {
+                    let left: Vec<usize> = (0..len).filter(|v| !used[*v]).collect();
+                    *left.choose(&mut rng).unwrap()
+                }
+            };
+        }
+
+        Permutation(child)
+    }
+}
+
+pub struct Swap;
+
+impl Mutation<Permutation> for Swap
+{
+    fn mutation(&self, pop: &mut Permutation) {
+        if pop.len() < 2 {
+            return;
+        }
+
+        let mut rng = thread_rng();
+        let (a, b) = (rng.gen_range(0..pop.len()), rng.gen_range(0..pop.len()));
+        pop.0.swap(a, b);
+    }
+}
+
+// Moves one value to another position
+pub struct Insertion;
+
+impl Mutation<Permutation> for Insertion
+{
+    fn mutation(&self, pop: &mut Permutation) {
+        if pop.len() < 2 {
+            return;
+        }
+
+        let mut rng = thread_rng();
+        let v = pop.0.remove(rng.gen_range(0..pop.len()));
+        pop.0.insert(rng.gen_range(0..=pop.0.len()), v);
+    }
+}
+
+// Reverses a random segment
+pub struct Inversion;
+
+impl Mutation<Permutation> for Inversion
+{
+    fn mutation(&self, pop: &mut Permutation) {
+        let (a, b) = cut_points(pop.len());
+        pop.0[a..b].reverse();
+    }
+}
+
+// Shuffles a random segment
+pub struct Scramble;
+
+impl Mutation<Permutation> for Scramble
+{
+    fn mutation(&self, pop: &mut Permutation) {
+        let (a, b) = cut_points(pop.len());
+        pop.0[a..b].shuffle(&mut thread_rng());
+    }
+}
+
+#[cfg(test)]
+fn assert_valid(perm: &Permutation, len: usize) {
+    assert_eq!(perm.len(), len);
+    assert!(Permutation::from_vec(perm.0.clone()).is_some(), "{perm} is not a permutation");
+}
+
+#[test]
+fn test_crossovers_keep_permutation() {
+    let crossovers: Vec<Box<dyn Crossover<Permutation>>> = vec![
+        Box::new(Pmx), Box::new(Ox1), Box::new(Cx), Box::new(Erx)
+    ];
+
+    for len in 0..40 {
+        for _ in 0..25 {
+            let (parent1, parent2) = (Permutation::random(len), Permutation::random(len));
+            for crossover in &crossovers {
+                let (child1, child2) = crossover.crossover_pair(&parent1, &parent2);
+                assert_valid(&child1, len);
+                assert_valid(&child2, len);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_mutations_keep_permutation() {
+    let mutations: Vec<Box<dyn Mutation<Permutation>>> = vec![
+        Box::new(Swap), Box::new(Insertion), Box::new(Inversion), Box::new(Scramble)
+    ];
+
+    for len in 0..40 {
+        let mut perm = Permutation::random(len);
+        for _ in 0..25 {
+            for mutation in &mutations {
+                mutation.mutation(&mut perm);
+                assert_valid(&perm, len);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_cx_positions_come_from_parents() {
+    let parent1 = Permutation::from_vec(vec![0, 1, 2, 3, 4, 5, 6, 7]).unwrap();
+    let parent2 = Permutation::from_vec(vec![7, 2, 5, 0, 1, 3, 4, 6]).unwrap();
+
+    let (child1, child2) = Cx.crossover_pair(&parent1, &parent2);
+    for i in 0..8 {
+        assert!(child1[i] == parent1[i] || child1[i] == parent2[i]);
+        assert!(child2[i] == parent1[i] || child2[i] == parent2[i]);
+    }
+}
@@ -0,0 +1,418 @@
+use std::f64::consts::PI;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::{thread_rng, Rng, seq::SliceRandom};
+
+use crate::crossover::Crossover;
+use crate::generator::Generator;
+use crate::mutation::Mutation;
+
+// Box-Muller, avoids pulling rand_distr for a single distribution
+pub fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Repair {
+    Clamp,
+    Reflect,
+    Wrap
+}
+
+#[derive(Clone, Debug)]
+pub struct Bounds {
+    pub lower: Vec<f64>,
+    pub upper: Vec<f64>,
+    pub repair: Repair
+}
+
+impl Bounds {
+
+    pub fn new(ranges: &[(f64, f64)], repair: Repair) -> Self {
+        Self {
+            lower: ranges.iter().map(|r| r.0).collect(),
+            upper: ranges.iter().map(|r| r.1).collect(),
+            repair
+        }
+    }
+
+    // Same range on every dimension
+    pub fn uniform(dim: usize, lower: f64, upper: f64, repair: Repair) -> Self {
+        Self {
+            lower: vec![lower; dim],
+            upper: vec![upper; dim],
+            repair
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.lower.len()
+    }
+
+    pub fn width(&self, i: usize) -> f64 {
+        self.upper[i] - self.lower[i]
+    }
+
+    // Brings a single gene back into its range
+    pub fn repair_gene(&self, i: usize, x: f64) -> f64 {
+        let (lo, hi) = (self.lower[i], self.upper[i]);
+        if (lo..=hi).contains(&x) || hi <= lo {
+            return x.clamp(lo, hi.max(lo));
+        }
+
+        let width = hi - lo;
+        match self.repair {
+            Repair::Clamp => x.clamp(lo, hi),
+            Repair::Wrap => lo + (x - lo).rem_euclid(width),
+            Repair::Reflect => {
+                // Folding over a period of twice the width
+                let folded = (x - lo).rem_euclid(2.0 * width);
+                if folded <= width { lo + folded } else { hi - (folded - width) }
+            }
+        }
+    }
+
+    pub fn repair(&self, genome: &mut [f64]) {
+        for (i, x) in genome.iter_mut().enumerate() {
+            *x = self.repair_gene(i, *x);
+        }
+    }
+}
+
+pub struct UniformReal {
+    pub bounds: Bounds
+}
+
+impl Generator<Vec<f64>> for UniformReal {
+
+    fn generator(&self) -> Vec<f64> {
+        let mut rng = thread_rng();
+
+        (0..self.bounds.dim())
+            .map(|i| self.bounds.lower[i] + rng.gen::<f64>() * self.bounds.width(i))
+            .collect()
+    }
+}
+
+// Each dimension is cut in `samples` strata and each batch of `samples`
+// generated individuals uses every stratum exactly once. A new batch is
+// shuffled once the previous one is used up.
+pub struct LatinHypercube {
+    pub bounds: Bounds,
+    pub samples: usize,
+    strata: Mutex<Vec<Vec<usize>>>
+}
+
+impl LatinHypercube {
+
+    pub fn new(bounds: Bounds, samples: usize) -> Self {
+        Self {
+            bounds,
+            samples: samples.max(1),
+            strata: Mutex::new(vec![])
+        }
+    }
+}
+
+impl Generator<Vec<f64>> for LatinHypercube {
+
+    fn generator(&self) -> Vec<f64> {
+        let mut rng = thread_rng();
+        let mut strata = self.strata.lock().unwrap();
+
+        if strata.first().is_none_or(|s| s.is_empty()) {
+            *strata = (0..self.bounds.dim()).map(|_| {
+                let mut s: Vec<usize> = (0..self.samples).collect();
+                s.shuffle(&mut rng);
+                s
+            }).collect();
+        }
+
+        strata.iter_mut().enumerate().map(|(i, s)| {
+            let stratum = s.pop().unwrap() as f64;
+            let step = self.bounds.width(i) / self.samples as f64;
+
+            self.bounds.lower[i] + (stratum + rng.gen::<f64>()) * step
+        }).collect()
+    }
+}
+
+// Simulated binary crossover, `eta` close to 0 spreads the children far from
+// their parents, large values keep them close
+pub struct Sbx {
+    pub eta: f64,
+    pub bounds: Bounds
+}
+
+impl Crossover<Vec<f64>> for Sbx
+{
+    fn crossover(&self, parent1: &Vec<f64>, parent2: &Vec<f64>) -> Vec<f64> {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &Vec<f64>, parent2: &Vec<f64>) -> (Vec<f64>, Vec<f64>) {
+        let mut rng = thread_rng();
+        let (mut child1, mut child2) = (parent1.clone(), parent2.clone());
+
+        for i in 0..parent1.len().min(parent2.len()) {
+            let u: f64 = rng.gen();
+            let beta = if u <= 0.5 {
+                (2.0 * u).powf(1.0 / (self.eta + 1.0))
+            } else {
+                (1.0 / (2.0 * (1.0 - u))).powf(1.0 / (self.eta + 1.0))
+            };
+
+            child1[i] = 0.5 * ((1.0 + beta) * parent1[i] + (1.0 - beta) * parent2[i]);
+            child2[i] = 0.5 * ((1.0 - beta) * parent1[i] + (1.0 + beta) * parent2[i]);
+        }
+        self.bounds.repair(&mut child1);
+        self.bounds.repair(&mut child2);
+
+        (child1, child2)
+    }
+}
+
+// Each gene is drawn in the parents' interval extended by `alpha` times its
+// width on both sides
+pub struct BlxAlpha {
+    pub alpha: f64,
+    pub bounds: Bounds
+}
+
+impl BlxAlpha {
+
+    fn child(&self, parent1: &[f64], parent2: &[f64]) -> Vec<f64> {
+        let mut rng = thread_rng();
+        let mut child: Vec<f64> = parent1.iter().zip(parent2.iter()).map(|(a, b)| {
+            let (lo, hi) = (a.min(*b), a.max(*b));
+            let d = hi - lo;
+
+            lo - self.alpha * d + rng.gen::<f64>() * (1.0 + 2.0 * self.alpha) * d
+        }).collect();
+        self.bounds.repair(&mut child);
+
+        child
+    }
+}
+
+impl Crossover<Vec<f64>> for BlxAlpha
+{
+    fn crossover(&self, parent1: &Vec<f64>, parent2: &Vec<f64>) -> Vec<f64> {
+        self.child(parent1, parent2)
+    }
+}
+
+// Genes from a random point onwards are the `alpha` weighted mean of both
+// parents
+pub struct Arithmetic {
+    pub alpha: f64
+}
+
+impl Crossover<Vec<f64>> for Arithmetic
+{
+    fn crossover(&self, parent1: &Vec<f64>, parent2: &Vec<f64>) -> Vec<f64> {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &Vec<f64>, parent2: &Vec<f64>) -> (Vec<f64>, Vec<f64>) {
+        let len = parent1.len().min(parent2.len());
+        let start = thread_rng().gen_range(0..len.max(1));
+
+        blend(parent1, parent2, self.alpha, start)
+    }
+}
+
+// Every gene is the `alpha` weighted mean of both parents
+pub struct WholeArithmetic {
+    pub alpha: f64
+}
+
+impl Crossover<Vec<f64>> for WholeArithmetic
+{
+    fn crossover(&self, parent1: &Vec<f64>, parent2: &Vec<f64>) -> Vec<f64> {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &Vec<f64>, parent2: &Vec<f64>) -> (Vec<f64>, Vec<f64>) {
+        blend(parent1, parent2, self.alpha, 0)
+    }
+}
+
+fn blend(parent1: &[f64], parent2: &[f64], alpha: f64, start: usize) -> (Vec<f64>, Vec<f64>) {
+    let (mut child1, mut child2) = (parent1.to_vec(), parent2.to_vec());
+
+    for i in start..parent1.len().min(parent2.len()) {
+        child1[i] = alpha * parent1[i] + (1.0 - alpha) * parent2[i];
+        child2[i] = alpha * parent2[i] + (1.0 - alpha) * parent1[i];
+    }
+
+    (child1, child2)
+}
+
+// Deb's polynomial mutation, each gene mutates with probability `rate`
+pub struct Polynomial {
+    pub eta: f64,
+    pub rate: f64,
+    pub bounds: Bounds
+}
+
+impl Mutation<Vec<f64>> for Polynomial
+{
+    fn mutation(&self, pop: &mut Vec<f64>) {
+        let mut rng = thread_rng();
+
+        for (i, x) in pop.iter_mut().enumerate() {
+            if !rng.gen_bool(self.rate) {
+                continue;
+            }
+
+            let u: f64 = rng.gen();
+            let delta = if u < 0.5 {
+                (2.0 * u).powf(1.0 / (self.eta + 1.0)) - 1.0
+            } else {
+                1.0 - (2.0 * (1.0 - u)).powf(1.0 / (self.eta + 1.0))
+            };
+            *x = self.bounds.repair_gene(i, *x + delta * self.bounds.width(i));
+        }
+    }
+}
+
+// Sigma of the gaussian mutation, relative to the width of each dimension.
+// A step is one call to `mutation`, so one mutated child, not a generation:
+// `generate` mutates about a quarter of the children, other runners each of
+// them. `steps` and `decay` are to be set for that number of mutated
+// children over the run. Children mutated in parallel take their steps in
+// no particular order.
+#[derive(Clone, Copy, Debug)]
+pub enum SigmaSchedule {
+    Constant(f64),
+    Linear { start: f64, end: f64, steps: usize },
+    Exponential { start: f64, decay: f64 }
+}
+
+impl SigmaSchedule {
+
+    pub fn sigma(&self, step: usize) -> f64 {
+        match *self {
+            SigmaSchedule::Constant(sigma) => sigma,
+            SigmaSchedule::Linear { start, end, steps } => {
+                let t = (step as f64 / steps.max(1) as f64).min(1.0);
+                start + (end - start) * t
+            },
+            SigmaSchedule::Exponential { start, decay } => start * decay.powf(step as f64)
+        }
+    }
+}
+
+pub struct Gaussian {
+    pub sigma: SigmaSchedule,
+    pub rate: f64,
+    pub bounds: Bounds,
+    steps: AtomicUsize
+}
+
+impl Gaussian {
+
+    pub fn new(sigma: SigmaSchedule, rate: f64, bounds: Bounds) -> Self {
+        Self {
+            sigma,
+            rate,
+            bounds,
+            steps: AtomicUsize::new(0)
+        }
+    }
+}
+
+impl Mutation<Vec<f64>> for Gaussian
+{
+    fn mutation(&self, pop: &mut Vec<f64>) {
+        let mut rng = thread_rng();
+        let sigma = self.sigma.sigma(self.steps.fetch_add(1, Ordering::Relaxed));
+
+        for (i, x) in pop.iter_mut().enumerate() {
+            if rng.gen_bool(self.rate) {
+                let step = standard_normal(&mut rng) * sigma * self.bounds.width(i);
+                *x = self.bounds.repair_gene(i, *x + step);
+            }
+        }
+    }
+}
+
+// Each gene is redrawn uniformly in its range with probability `rate`
+pub struct UniformReset {
+    pub rate: f64,
+    pub bounds: Bounds
+}
+
+impl Mutation<Vec<f64>> for UniformReset
+{
+    fn mutation(&self, pop: &mut Vec<f64>) {
+        let mut rng = thread_rng();
+
+        for (i, x) in pop.iter_mut().enumerate() {
+            if rng.gen_bool(self.rate) {
+                *x = self.bounds.lower[i] + rng.gen::<f64>() * self.bounds.width(i);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_repair() {
+    let clamp = Bounds::uniform(1, 0.0, 10.0, Repair::Clamp);
+    let reflect = Bounds::uniform(1, 0.0, 10.0, Repair::Reflect);
+    let wrap = Bounds::uniform(1, 0.0, 10.0, Repair::Wrap);
+
+    assert_eq!(clamp.repair_gene(0, 12.0), 10.0);
+    assert_eq!(reflect.repair_gene(0, 12.0), 8.0);
+    assert_eq!(reflect.repair_gene(0, -3.0), 3.0);
+    assert_eq!(wrap.repair_gene(0, 12.0), 2.0);
+    assert_eq!(wrap.repair_gene(0, -3.0), 7.0);
+}
+
+#[test]
+fn test_operators_stay_in_bounds() {
+    let bounds = Bounds::uniform(5, -1.0, 1.0, Repair::Reflect);
+    let generator = LatinHypercube::new(bounds.clone(), 10);
+    let sbx = Sbx{ eta: 0.5, bounds: bounds.clone() };
+    let blx = BlxAlpha{ alpha: 0.5, bounds: bounds.clone() };
+    let gaussian = Gaussian::new(SigmaSchedule::Constant(2.0), 1.0, bounds.clone());
+
+    for _ in 0..100 {
+        let (parent1, parent2) = (generator.generator(), generator.generator());
+        let (mut child1, child2) = sbx.crossover_pair(&parent1, &parent2);
+        gaussian.mutation(&mut child1);
+
+        for x in child1.iter().chain(child2.iter()).chain(blx.crossover(&parent1, &parent2).iter()) {
+            assert!((-1.0..=1.0).contains(x));
+        }
+    }
+}
+
+#[test]
+fn test_latin_hypercube_strata() {
+    let generator = LatinHypercube::new(Bounds::uniform(3, 0.0, 10.0, Repair::Clamp), 10);
+
+    let samples: Vec<Vec<f64>> = (0..10).map(|_| generator.generator()).collect();
+    for d in 0..3 {
+        let mut strata: Vec<usize> = samples.iter().map(|s| s[d] as usize).collect();
+        strata.sort_unstable();
+        assert_eq!(strata, (0..10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,222 @@
+use rand::thread_rng;
+
+use crate::crossover::Crossover;
+use crate::evaluator::Evaluator;
+use crate::generator::Generator;
+use crate::genome::real::{standard_normal, Bounds};
+use crate::mutation::Mutation;
+
+// Individual carrying its own mutation parameters, evolved along with the
+// genome as in evolution strategies
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfAdaptive<T, S> {
+    pub genome: T,
+    pub strategy: S
+}
+
+// Real genome with either one step size or one per gene
+pub type StepSizes = SelfAdaptive<Vec<f64>, Vec<f64>>;
+
+// Any genome with its own mutation rate
+pub type WithRate<T> = SelfAdaptive<T, f64>;
+
+// Strategy parameters are recombined by geometric mean, they are scales
+pub trait StrategyParams: Clone {
+    fn recombine(&self, other: &Self) -> Self;
+}
+
+impl StrategyParams for f64 {
+
+    fn recombine(&self, other: &Self) -> Self {
+        (self * other).sqrt()
+    }
+}
+
+impl StrategyParams for Vec<f64> {
+
+    fn recombine(&self, other: &Self) -> Self {
+        self.iter().zip(other.iter()).map(|(a, b)| a.recombine(b)).collect()
+    }
+}
+
+// Rates the genome only, the strategy doesn't change the fitness
+pub struct GenomeOnly<E>(pub E);
+
+impl<T, S, E: Evaluator<T>> Evaluator<SelfAdaptive<T, S>> for GenomeOnly<E> {
+
+    fn evaluator(&self, pop: &SelfAdaptive<T, S>) -> f32 {
+        self.0.evaluator(&pop.genome)
+    }
+}
+
+pub struct StepSizeGenerator<G> {
+    pub genes: G,
+    pub initial_sigma: f64,
+    // One step size per gene, or a single one for the whole genome (and for
+    // an empty one)
+    pub per_gene: bool
+}
+
+impl<G: Generator<Vec<f64>>> Generator<StepSizes> for StepSizeGenerator<G> {
+
+    fn generator(&self) -> StepSizes {
+        let genome = self.genes.generator();
+        let count = if self.per_gene { genome.len().max(1) } else { 1 };
+
+        SelfAdaptive {
+            genome,
+            strategy: vec![self.initial_sigma; count]
+        }
+    }
+}
+
+pub struct RateGenerator<G> {
+    pub genes: G,
+    pub initial_rate: f64
+}
+
+impl<T, G: Generator<T>> Generator<WithRate<T>> for RateGenerator<G> {
+
+    fn generator(&self) -> WithRate<T> {
+        SelfAdaptive {
+            genome: self.genes.generator(),
+            strategy: self.initial_rate
+        }
+    }
+}
+
+// Genes go through `genes`, both children get the recombined strategy
+pub struct StrategyCrossover<C> {
+    pub genes: C
+}
+
+impl<T, S, C> Crossover<SelfAdaptive<T, S>> for StrategyCrossover<C>
+where
+    S: StrategyParams,
+    C: Crossover<T> {
+
+    fn crossover(&self, parent1: &SelfAdaptive<T, S>, parent2: &SelfAdaptive<T, S>) -> SelfAdaptive<T, S> {
+        SelfAdaptive {
+            genome: self.genes.crossover(&parent1.genome, &parent2.genome),
+            strategy: parent1.strategy.recombine(&parent2.strategy)
+        }
+    }
+
+    fn children(&self) -> usize {
+        self.genes.children()
+    }
+
+    fn crossover_pair(&self, parent1: &SelfAdaptive<T, S>, parent2: &SelfAdaptive<T, S>)
+        -> (SelfAdaptive<T, S>, SelfAdaptive<T, S>) {
+
+        let (genome1, genome2) = self.genes.crossover_pair(&parent1.genome, &parent2.genome);
+        let strategy = parent1.strategy.recombine(&parent2.strategy);
+
+        (SelfAdaptive{ genome: genome1, strategy: strategy.clone() }, SelfAdaptive{ genome: genome2, strategy })
+    }
+}
+
+// Log-normal rule: step sizes are mutated first, sigma' = sigma * exp(t0 * N
+// + t * N_i) with t0 = 1 / sqrt(2n) and t = 1 / sqrt(2 sqrt(n)) (a single
+// step size uses t0 = 1 / sqrt(n)), then each gene moves by sigma'_i * N_i
+pub struct LogNormalStepSizes {
+    pub bounds: Bounds,
+    pub min_sigma: f64
+}
+
+impl Mutation<StepSizes> for LogNormalStepSizes
+{
+    fn mutation(&self, pop: &mut StepSizes) {
+        assert!(!pop.strategy.is_empty(), "step sizes need at least one sigma");
+
+        let mut rng = thread_rng();
+        let n = pop.genome.len().max(1) as f64;
+
+        if pop.strategy.len() == 1 {
+            let sigma = &mut pop.strategy[0];
+            *sigma = (*sigma * (standard_normal(&mut rng) / n.sqrt()).exp()).max(self.min_sigma);
+        } else {
+            let global = standard_normal(&mut rng) / (2.0 * n).sqrt();
+            let tau = 1.0 / (2.0 * n.sqrt()).sqrt();
+            for sigma in pop.strategy.iter_mut() {
+                *sigma = (*sigma * (global + tau * standard_normal(&mut rng)).exp()).max(self.min_sigma);
+            }
+        }
+
+        for (i, x) in pop.genome.iter_mut().enumerate() {
+            let sigma = pop.strategy[i.min(pop.strategy.len() - 1)];
+            *x = self.bounds.repair_gene(i, *x + sigma * standard_normal(&mut rng));
+        }
+    }
+}
+
+// The rate is mutated first, rate' = rate * exp(tau * N) kept within
+// min_rate..=max_rate, then the mutation built from it is applied to the
+// genome, e.g. `build: |rate| BitFlip{ rate }`
+pub struct LogNormalRate<F> {
+    pub build: F,
+    pub tau: f64,
+    pub min_rate: f64,
+    pub max_rate: f64
+}
+
+impl<T, M, F> Mutation<WithRate<T>> for LogNormalRate<F>
+where
+    M: Mutation<T>,
+    F: Fn(f64) -> M {
+
+    fn mutation(&self, pop: &mut WithRate<T>) {
+        let noise = self.tau * standard_normal(&mut thread_rng());
+        pop.strategy = (pop.strategy * noise.exp()).clamp(self.min_rate, self.max_rate);
+
+        (self.build)(pop.strategy).mutation(&mut pop.genome);
+    }
+}
+
+#[test]
+fn test_step_sizes() {
+    use crate::genome::real::{Repair, UniformReal, WholeArithmetic};
+
+    let bounds = Bounds::uniform(4, -1.0, 1.0, Repair::Clamp);
+    let generator = StepSizeGenerator{ genes: UniformReal{ bounds: bounds.clone() }, initial_sigma: 0.5, per_gene: true };
+    let crossover = StrategyCrossover{ genes: WholeArithmetic{ alpha: 0.5 } };
+    let mutation = LogNormalStepSizes{ bounds, min_sigma: 0.01 };
+
+    let mut parent1 = generator.generator();
+    parent1.strategy = vec![0.1, 0.2, 0.4, 0.8];
+    let parent2 = generator.generator();
+
+    let mut child = crossover.crossover(&parent1, &parent2);
+    for (sigma, expected) in child.strategy.iter().zip([0.1f64 * 0.5, 0.2 * 0.5, 0.4 * 0.5, 0.8 * 0.5]) {
+        assert!((sigma - expected.sqrt()).abs() < 1e-12);
+    }
+
+    for _ in 0..100 {
+        mutation.mutation(&mut child);
+        assert!(child.strategy.iter().all(|s| *s >= 0.01));
+        assert!(child.genome.iter().all(|x| (-1.0..=1.0).contains(x)));
+    }
+}
+
+#[test]
+#[should_panic(expected = "at least one sigma")]
+fn test_no_step_size() {
+    use crate::genome::real::Repair;
+
+    let mutation = LogNormalStepSizes{ bounds: Bounds::uniform(2, -1.0, 1.0, Repair::Clamp), min_sigma: 0.01 };
+    mutation.mutation(&mut SelfAdaptive{ genome: vec![0.0, 0.0], strategy: vec![] });
+}
+
+#[test]
+fn test_rate() {
+    use crate::genome::bits::{BitFlip, RandomBits};
+
+    let generator = RateGenerator{ genes: RandomBits{ len: 32 }, initial_rate: 0.1 };
+    let mutation = LogNormalRate{ build: |rate| BitFlip{ rate }, tau: 0.5, min_rate: 0.001, max_rate: 0.5 };
+
+    let mut pop = generator.generator();
+    for _ in 0..100 {
+        mutation.mutation(&mut pop);
+        assert!((0.001..=0.5).contains(&pop.strategy));
+    }
+}
@@ -0,0 +1,505 @@
+use std::sync::Arc;
+
+use rand::{thread_rng, Rng, seq::SliceRandom};
+
+use crate::crossover::Crossover;
+use crate::evaluator::Evaluator;
+use crate::generator::Generator;
+use crate::mutation::Mutation;
+use crate::selector::Selector;
+
+// Types are plain ids chosen by the user, untyped problems use 0 everywhere
+pub type Type = usize;
+
+pub struct Function<V> {
+    pub name: String,
+    pub args: Vec<Type>,
+    pub ret: Type,
+    pub apply: fn(&[V]) -> V
+}
+
+pub enum TerminalKind<V> {
+    // Index in the variables given to `Tree::evaluate`
+    Variable(usize),
+    Constant(V),
+    // Drawn once when the node is created, then kept as a constant
+    Ephemeral(fn() -> V)
+}
+
+pub struct Terminal<V> {
+    pub name: String,
+    pub ret: Type,
+    pub kind: TerminalKind<V>
+}
+
+pub struct PrimitiveSet<V> {
+    pub functions: Vec<Function<V>>,
+    pub terminals: Vec<Terminal<V>>,
+    // Type returned by whole trees
+    pub root: Type
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node<V> {
+    Function(usize),
+    Terminal(usize),
+    // Ephemeral terminal and the value it drew
+    Ephemeral(usize, V)
+}
+
+impl<V: Clone> PrimitiveSet<V> {
+
+    pub fn arity(&self, node: &Node<V>) -> usize {
+        match node {
+            Node::Function(f) => self.functions[*f].args.len(),
+            _ => 0
+        }
+    }
+
+    pub fn node_type(&self, node: &Node<V>) -> Type {
+        match node {
+            Node::Function(f) => self.functions[*f].ret,
+            Node::Terminal(t) | Node::Ephemeral(t, _) => self.terminals[*t].ret
+        }
+    }
+
+    fn terminal(&self, t: usize) -> Node<V> {
+        match &self.terminals[t].kind {
+            TerminalKind::Ephemeral(draw) => Node::Ephemeral(t, draw()),
+            _ => Node::Terminal(t)
+        }
+    }
+
+    fn random_terminal<R: Rng>(&self, ty: Type, rng: &mut R) -> Node<V> {
+        let terminals: Vec<usize> = (0..self.terminals.len())
+            .filter(|t| self.terminals[*t].ret == ty)
+            .collect();
+        let t = *terminals.choose(rng).unwrap_or_else(|| panic!("no terminal returns type {ty}"));
+
+        self.terminal(t)
+    }
+
+    // Appends a random subtree of type `ty` in prefix order. `full` trees
+    // only put terminals at `depth`, grown ones may stop earlier.
+    fn grow<R: Rng>(&self, ty: Type, depth: usize, full: bool, rng: &mut R, out: &mut Vec<Node<V>>) {
+        let functions: Vec<usize> = (0..self.functions.len())
+            .filter(|f| self.functions[*f].ret == ty)
+            .collect();
+        let terminals = self.terminals.iter().filter(|t| t.ret == ty).count();
+
+        let ratio = functions.len() as f64 / (functions.len() + terminals).max(1) as f64;
+        if depth > 0 && !functions.is_empty() && (full || rng.gen_bool(ratio)) {
+            let f = *functions.choose(rng).unwrap();
+            out.push(Node::Function(f));
+            for arg in &self.functions[f].args {
+                self.grow(*arg, depth - 1, full, rng, out);
+            }
+        } else {
+            out.push(self.random_terminal(ty, rng));
+        }
+    }
+}
+
+// Tree stored in prefix order, a subtree is a contiguous slice
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tree<V> {
+    pub nodes: Vec<Node<V>>
+}
+
+impl<V: Clone> Tree<V> {
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // End (exclusive) of the subtree starting at `i`
+    pub fn subtree_end(&self, set: &PrimitiveSet<V>, i: usize) -> usize {
+        let mut open = 1;
+        let mut end = i;
+        while open > 0 {
+            open += set.arity(&self.nodes[end]);
+            open -= 1;
+            end += 1;
+        }
+
+        end
+    }
+
+    // Depth of each node, the root being at 0
+    pub fn depths(&self, set: &PrimitiveSet<V>) -> Vec<usize> {
+        let mut depths = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<(usize, usize)> = vec![];
+
+        for node in &self.nodes {
+            let depth = match stack.last_mut() {
+                Some((depth, left)) => {
+                    *left -= 1;
+                    *depth + 1
+                },
+                None => 0
+            };
+            while stack.last().is_some_and(|(_, left)| *left == 0) {
+                stack.pop();
+            }
+
+            depths.push(depth);
+            let arity = set.arity(node);
+            if arity > 0 {
+                stack.push((depth, arity));
+            }
+        }
+
+        depths
+    }
+
+    pub fn depth(&self, set: &PrimitiveSet<V>) -> usize {
+        self.depths(set).into_iter().max().unwrap_or(0)
+    }
+
+    pub fn evaluate(&self, set: &PrimitiveSet<V>, vars: &[V]) -> V {
+        self.evaluate_at(set, vars, 0).0
+    }
+
+    fn evaluate_at(&self, set: &PrimitiveSet<V>, vars: &[V], i: usize) -> (V, usize) {
+        match &self.nodes[i] {
+            Node::Function(f) => {
+                let mut args = Vec::with_capacity(set.functions[*f].args.len());
+                let mut next = i + 1;
+                for _ in 0..set.functions[*f].args.len() {
+                    let (value, end) = self.evaluate_at(set, vars, next);
+                    args.push(value);
+                    next = end;
+                }
+
+                ((set.functions[*f].apply)(&args), next)
+            },
+            Node::Terminal(t) => match &set.terminals[*t].kind {
+                TerminalKind::Variable(v) => (vars[*v].clone(), i + 1),
+                TerminalKind::Constant(c) => (c.clone(), i + 1),
+                TerminalKind::Ephemeral(draw) => (draw(), i + 1)
+            },
+            Node::Ephemeral(_, value) => (value.clone(), i + 1)
+        }
+    }
+
+    // S-expression, e.g. (add x (mul x 2))
+    pub fn to_sexpr(&self, set: &PrimitiveSet<V>) -> String
+    where
+        V: std::fmt::Display {
+
+        self.sexpr_at(set, 0).0
+    }
+
+    fn sexpr_at(&self, set: &PrimitiveSet<V>, i: usize) -> (String, usize)
+    where
+        V: std::fmt::Display {
+
+        match &self.nodes[i] {
+            Node::Function(f) => {
+                let mut parts = vec![set.functions[*f].name.clone()];
+                let mut next = i + 1;
+                for _ in 0..set.functions[*f].args.len() {
+                    let (part, end) = self.sexpr_at(set, next);
+                    parts.push(part);
+                    next = end;
+                }
+
+                (format!("({})", parts.join(" ")), next)
+            },
+            Node::Terminal(t) => (set.terminals[*t].name.clone(), i + 1),
+            Node::Ephemeral(_, value) => (value.to_string(), i + 1)
+        }
+    }
+
+    fn replace(&self, start: usize, end: usize, subtree: &[Node<V>]) -> Tree<V> {
+        let mut nodes = Vec::with_capacity(self.nodes.len() - (end - start) + subtree.len());
+        nodes.extend_from_slice(&self.nodes[..start]);
+        nodes.extend_from_slice(subtree);
+        nodes.extend_from_slice(&self.nodes[end..]);
+
+        Tree { nodes }
+    }
+}
+
+// Half the trees are full, half are grown, with depths spread evenly between
+// `min_depth` and `max_depth`
+pub struct RampedHalfAndHalf<V> {
+    pub set: Arc<PrimitiveSet<V>>,
+    pub min_depth: usize,
+    pub max_depth: usize
+}
+
+impl<V: Clone> Generator<Tree<V>> for RampedHalfAndHalf<V> {
+
+    fn generator(&self) -> Tree<V> {
+        let mut rng = thread_rng();
+        let depth = rng.gen_range(self.min_depth..=self.max_depth.max(self.min_depth));
+        let mut nodes = vec![];
+        self.set.grow(self.set.root, depth, rng.gen_bool(0.5), &mut rng, &mut nodes);
+
+        Tree { nodes }
+    }
+}
+
+// Swaps two subtrees of the same type. A child deeper than `max_depth` is
+// replaced by its parent.
+pub struct SubtreeCrossover<V> {
+    pub set: Arc<PrimitiveSet<V>>,
+    pub max_depth: usize
+}
+
+impl<V: Clone> Crossover<Tree<V>> for SubtreeCrossover<V>
+{
+    fn crossover(&self, parent1: &Tree<V>, parent2: &Tree<V>) -> Tree<V> {
+        self.crossover_pair(parent1, parent2).0
+    }
+
+    fn children(&self) -> usize {
+        2
+    }
+
+    fn crossover_pair(&self, parent1: &Tree<V>, parent2: &Tree<V>) -> (Tree<V>, Tree<V>) {
+        let mut rng = thread_rng();
+        let set = &self.set;
+
+        let i = rng.gen_range(0..parent1.len());
+        let ty = set.node_type(&parent1.nodes[i]);
+        let candidates: Vec<usize> = (0..parent2.len())
+            .filter(|j| set.node_type(&parent2.nodes[*j]) == ty)
+            .collect();
+        let Some(j) = candidates.choose(&mut rng).copied() else {
+            return (parent1.clone(), parent2.clone());
+        };
+
+        let (end_i, end_j) = (parent1.subtree_end(set, i), parent2.subtree_end(set, j));
+        let child1 = parent1.replace(i, end_i, &parent2.nodes[j..end_j]);
+        let child2 = parent2.replace(j, end_j, &parent1.nodes[i..end_i]);
+
+        let keep = |child: Tree<V>, parent: &Tree<V>| {
+            if child.depth(set) > self.max_depth { parent.clone() } else { child }
+        };
+
+        (keep(child1, parent1), keep(child2, parent2))
+    }
+}
+
+// Each node is replaced with probability `rate` by another primitive with
+// the same signature
+pub struct PointMutation<V> {
+    pub set: Arc<PrimitiveSet<V>>,
+    pub rate: f64
+}
+
+impl<V: Clone> Mutation<Tree<V>> for PointMutation<V>
+{
+    fn mutation(&self, pop: &mut Tree<V>) {
+        let mut rng = thread_rng();
+        let set = &self.set;
+
+        for node in pop.nodes.iter_mut() {
+            if !rng.gen_bool(self.rate) {
+                continue;
+            }
+
+            let ty = set.node_type(node);
+            *node = match node {
+                Node::Function(f) => {
+                    let args = &set.functions[*f].args;
+                    let same: Vec<usize> = (0..set.functions.len())
+                        .filter(|g| set.functions[*g].ret == ty && &set.functions[*g].args == args)
+                        .collect();
+                    Node::Function(*same.choose(&mut rng).unwrap())
+                },
+                _ => set.random_terminal(ty, &mut rng)
+            };
+        }
+    }
+}
+
+// Replaces a random subtree by a new grown one of at most `depth`
+pub struct SubtreeMutation<V> {
+    pub set: Arc<PrimitiveSet<V>>,
+    pub depth: usize,
+    pub max_depth: usize
+}
+
+impl<V: Clone> Mutation<Tree<V>> for SubtreeMutation<V>
+{
+    fn mutation(&self, pop: &mut Tree<V>) {
+        let mut rng = thread_rng();
+        let set = &self.set;
+
+        let i = rng.gen_range(0..pop.len());
+        let mut subtree = vec![];
+        set.grow(set.node_type(&pop.nodes[i]), self.depth, false, &mut rng, &mut subtree);
+
+        let mutated = pop.replace(i, pop.subtree_end(set, i), &subtree);
+        if mutated.depth(set) <= self.max_depth {
+            *pop = mutated;
+        }
+    }
+}
+
+// Replaces the tree by one of its subtrees returning the root type
+pub struct HoistMutation<V> {
+    pub set: Arc<PrimitiveSet<V>>
+}
+
+impl<V: Clone> Mutation<Tree<V>> for HoistMutation<V>
+{
+    fn mutation(&self, pop: &mut Tree<V>) {
+        let set = &self.set;
+        let candidates: Vec<usize> = (0..pop.len())
+            .filter(|i| set.node_type(&pop.nodes[*i]) == set.root)
+            .collect();
+
+        let i = *candidates.choose(&mut thread_rng()).unwrap();
+        pop.nodes = pop.nodes[i..pop.subtree_end(set, i)].to_vec();
+    }
+}
+
+// Replaces a random function node and its subtree by a terminal
+pub struct ShrinkMutation<V> {
+    pub set: Arc<PrimitiveSet<V>>
+}
+
+impl<V: Clone> Mutation<Tree<V>> for ShrinkMutation<V>
+{
+    fn mutation(&self, pop: &mut Tree<V>) {
+        let mut rng = thread_rng();
+        let set = &self.set;
+        let functions: Vec<usize> = (0..pop.len())
+            .filter(|i| matches!(pop.nodes[*i], Node::Function(_)))
+            .collect();
+
+        if let Some(i) = functions.choose(&mut rng).copied() {
+            let terminal = set.random_terminal(set.node_type(&pop.nodes[i]), &mut rng);
+            *pop = pop.replace(i, pop.subtree_end(set, i), &[terminal]);
+        }
+    }
+}
+
+// Lexicographic parsimony pressure: tournaments on the rating, ties going to
+// the smallest tree
+pub struct LexicographicTournament {
+    pub size: usize,
+    pub max_pop: usize
+}
+
+impl LexicographicTournament {
+
+    fn tournament(&self, ratings: &[f32], sizes: Option<&[usize]>) -> Vec<usize> {
+        let mut rng = thread_rng();
+
+        (0..self.max_pop).map(|_| {
+            (0..self.size.max(1))
+                .map(|_| rng.gen_range(0..ratings.len()))
+                .reduce(|best, i| {
+                    let smaller = sizes.is_some_and(|s| s[i] < s[best]);
+                    if ratings[i] > ratings[best] || (ratings[i] == ratings[best] && smaller) { i } else { best }
+                })
+                .unwrap()
+        }).collect()
+    }
+}
+
+impl<V: Clone> Selector<Tree<V>> for LexicographicTournament {
+
+    fn select(&self, ratings: &[f32]) -> Vec<usize> {
+        self.tournament(ratings, None)
+    }
+
+    fn select_from(&self, pop: &[Tree<V>], ratings: &[f32]) -> Vec<usize> {
+        let sizes: Vec<usize> = pop.iter().map(|t| t.len()).collect();
+
+        self.tournament(ratings, Some(&sizes))
+    }
+}
+
+// Rating of 1 / (1 + mean squared error) over the samples, each sample being
+// the variables and the expected output
+pub struct SymbolicRegression {
+    pub set: Arc<PrimitiveSet<f64>>,
+    pub samples: Vec<(Vec<f64>, f64)>
+}
+
+impl Evaluator<Tree<f64>> for SymbolicRegression {
+
+    fn evaluator(&self, pop: &Tree<f64>) -> f32 {
+        let mse = self.samples.iter()
+            .map(|(vars, expected)| (pop.evaluate(&self.set, vars) - expected).powi(2))
+            .sum::<f64>() / self.samples.len().max(1) as f64;
+
+        if mse.is_finite() { (1.0 / (1.0 + mse)) as f32 } else { 0.0 }
+    }
+}
+
+#[cfg(test)]
+fn arithmetic_set() -> Arc<PrimitiveSet<f64>> {
+    Arc::new(PrimitiveSet {
+        functions: vec![
+            Function{ name: String::from("add"), args: vec![0, 0], ret: 0, apply: |a| a[0] + a[1] },
+            Function{ name: String::from("mul"), args: vec![0, 0], ret: 0, apply: |a| a[0] * a[1] },
+            Function{ name: String::from("neg"), args: vec![0], ret: 0, apply: |a| -a[0] }
+        ],
+        terminals: vec![
+            Terminal{ name: String::from("x"), ret: 0, kind: TerminalKind::Variable(0) },
+            Terminal{ name: String::from("rand"), ret: 0, kind: TerminalKind::Ephemeral(|| thread_rng().gen_range(-1.0..1.0)) }
+        ],
+        root: 0
+    })
+}
+
+#[test]
+fn test_evaluate() {
+    let set = arithmetic_set();
+    // (add x (mul x (neg x)))
+    let tree = Tree{ nodes: vec![
+        Node::Function(0), Node::Terminal(0), Node::Function(1), Node::Terminal(0),
+        Node::Function(2), Node::Terminal(0)
+    ] };
+
+    assert_eq!(tree.evaluate(&set, &[3.0]), 3.0 - 9.0);
+    assert_eq!(tree.depth(&set), 3);
+    assert_eq!(tree.subtree_end(&set, 2), 6);
+    assert_eq!(tree.to_sexpr(&set), "(add x (mul x (neg x)))");
+}
+
+#[test]
+fn test_operators_respect_depth() {
+    let set = arithmetic_set();
+    let generator = RampedHalfAndHalf{ set: set.clone(), min_depth: 1, max_depth: 4 };
+    let crossover = SubtreeCrossover{ set: set.clone(), max_depth: 5 };
+    let mutations: Vec<Box<dyn Mutation<Tree<f64>>>> = vec![
+        Box::new(PointMutation{ set: set.clone(), rate: 0.3 }),
+        Box::new(SubtreeMutation{ set: set.clone(), depth: 3, max_depth: 5 }),
+        Box::new(HoistMutation{ set: set.clone() }),
+        Box::new(ShrinkMutation{ set: set.clone() })
+    ];
+
+    for _ in 0..200 {
+        let (parent1, parent2) = (generator.generator(), generator.generator());
+        assert!(parent1.depth(&set) <= 4);
+
+        let (mut child, _) = crossover.crossover_pair(&parent1, &parent2);
+        for mutation in &mutations {
+            mutation.mutation(&mut child);
+            assert!(child.depth(&set) <= 5);
+            assert_eq!(child.subtree_end(&set, 0), child.len());
+        }
+    }
+}
+
+#[test]
+fn test_lexicographic_ties() {
+    let small: Tree<f64> = Tree{ nodes: vec![Node::Terminal(0)] };
+    let big = Tree{ nodes: vec![Node::Function(2), Node::Terminal(0)] };
+    let selector = LexicographicTournament{ size: 20, max_pop: 10 };
+
+    let selected = selector.select_from(&[big, small], &[1.0, 1.0]);
+    assert!(selected.iter().all(|i| *i == 1));
+}
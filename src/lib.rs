@@ -1,34 +1,51 @@
-use std::{fmt::Display};
-
 use rand::{thread_rng, Rng};
 
 pub mod criterion;
+pub mod state;
 pub mod selector;
+pub mod mating;
 pub mod evaluator;
 pub mod generator;
 pub mod crossover;
 pub mod mutation;
+pub mod adaptive;
+pub mod genome;
+pub mod gp;
+pub mod ge;
+pub mod es;
+pub mod de;
+pub mod pso;
+pub mod memetic;
+pub mod anneal;
+pub mod tabu;
+pub mod neat;
+pub mod qd;
+pub mod coevolution;
+pub mod result;
 
 use criterion::Criterion;
+use state::RunState;
 use selector::Selector;
+use mating::Mating;
 use evaluator::Evaluator;
 use generator::Generator;
-use crossover::Crossover;
+use crossover::MultiCrossover;
 use mutation::Mutation;
+use memetic::{LocalSearch, Memetic, NoLocalSearch, Target, Inheritance};
+use result::{Archives, HallOfFame, RunResult};
 
 const NUM_THREADS: u32 = 8;
+// Individuals kept in the hall of fame by `generate` and `generate_memetic`
+pub const HALL_OF_FAME_SIZE: usize = 10;
 
-fn fill_ratings<T, E>(pop_size: u32, pop: &Vec<T>, evaluator: &E, ratings: &mut Vec<f32>)
+fn fill_ratings<T, E>(pop_size: u32, pop: &[T], evaluator: &E, ratings: &mut Vec<f32>)
 where
     E: Evaluator<T> + Send + Sync,
     T: Send + Sync {
 
     std::thread::scope(|scope| {
         let mut pop_handled = pop_size / NUM_THREADS;
-        let mut offset = 0;
-        if pop_size % NUM_THREADS != 0 {
-            offset += pop_size % NUM_THREADS;
-        }
+        let offset = pop_size % NUM_THREADS;
         let mut threads = vec![];
 
         for thread in 0..NUM_THREADS {
@@ -59,19 +76,163 @@ where
     });
 }
 
-pub fn generate<T, G, E, S, C, M, F>(generator: &G, evaluator: &E, selector: &S,
-    crossover: &C, mutation: &M, stop_crit: &mut F , pop_size: u32) -> (T, i32, f32)
+fn best_index(ratings: &[f32]) -> (usize, f32) {
+    let (mut best, mut index) = (f32::MIN, 0);
+    ratings.iter().enumerate().for_each(|(i, v)| if *v > best {best = *v; index = i;});
+
+    (index, best)
+}
+
+// Best parent rating and children of each crossover, to credit the
+// operators once the children are rated
+pub(crate) type Crossed = Vec<(f32, std::ops::Range<usize>)>;
+
+// Last path segment of a type name, without its generics, to name operators
+fn short_type_name<T: ?Sized>() -> String {
+    let name = std::any::type_name::<T>();
+    let name = &name[..name.find('<').unwrap_or(name.len())];
+
+    name.rsplit("::").next().unwrap_or(name).to_string()
+}
+
+// New generation of `pop_size` from the fittest individuals of `pop`, with
+// the crossovers made and the children mutated. Also gives the best rating
+// of the parents of every crossover and of every mutated child, to credit
+// the operators with.
+pub(crate) fn breed<T, S, P, C, M>(pop: &[T], ratings: &[f32], selector: &S, mating: &P, crossover: &C,
+    mutation: &M, pop_size: usize) -> (Vec<T>, Crossed, Vec<(f32, usize)>)
+where
+    S: Selector<T>,
+    P: Mating<T>,
+    C: MultiCrossover<T>,
+    M: Mutation<T> {
+
+    let mut rng = thread_rng();
+    let parents = selector.select_from(pop, ratings);
+
+    let mut next = Vec::with_capacity(pop_size);
+    let mut crossed = vec![];
+    let mut mutated = vec![];
+    while next.len() < pop_size {
+        
+        let mates = mating.mating(pop, ratings, &parents, crossover.parents());
+        let parent_rating = mates.iter().map(|i| ratings[*i]).fold(f32::MIN, f32::max);
+        let mates: Vec<&T> = mates.into_iter().map(|i| &pop[i]).collect();
+        let first = next.len();
+
+        // Crossing the parents to generate new elements, the extra
+        // children of the last crossover are dropped
+        let children = crossover.multi_crossover(&mates);
+        assert!(!children.is_empty(), "crossover gave no child, the generation could never be filled");
+        for mut child in children {
+            if next.len() == pop_size {
+                break;
+            }
+
+            // Chances of mutation happening
+            if rng.gen_range(1..=100) < 25 {
+                // Mutating the new element
+                mutation.mutation(&mut child);
+                mutated.push((parent_rating, next.len()));
+            }
+            next.push(child);
+        }
+        crossed.push((parent_rating, first..next.len()));
+    }
+
+    (next, crossed, mutated)
+}
+
+// Credits each crossover with how much its best child improved on the best
+// rating of its parents, and each mutation with how much its child did, once
+// the children are rated
+pub(crate) fn credit<T, C, M>(crossover: &C, mutation: &M, crossed: Crossed, mutated: &[(f32, usize)], ratings: &[f32])
+where
+    C: MultiCrossover<T>,
+    M: Mutation<T> {
+
+    let improvements: Vec<f32> = crossed.into_iter()
+        .map(|(parent, children)| if children.is_empty() {
+            0.0
+        } else {
+            best_index(&ratings[children]).1 - parent
+        })
+        .collect();
+    crossover.credit(&improvements);
+    let improvements: Vec<f32> = mutated.iter().map(|(parent, child)| ratings[*child] - parent).collect();
+    mutation.credit(&improvements);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn generate<T, G, E, S, P, C, M, F>(generator: &G, evaluator: &E, selector: &S, mating: &P,
+    crossover: &C, mutation: &M, stop_crit: &mut F , pop_size: u32) -> RunResult<T>
 where 
     G: Generator<T>,
     E: Evaluator<T> + Send + Sync,
     F: Criterion,
     S: Selector<T>,
-    C: Crossover<T>,
+    P: Mating<T>,
+    C: MultiCrossover<T>,
     M: Mutation<T>,
-    T: Display + Send + Sync {
+    T: Clone + Send + Sync {
 
-        
-    let mut rng = thread_rng();
+    let memetic = Memetic {
+        local_search: NoLocalSearch,
+        probability: 0.0,
+        budget: 0,
+        target: Target::Offspring,
+        inheritance: Inheritance::Lamarckian
+    };
+
+    generate_memetic(generator, evaluator, selector, mating, crossover, mutation, &memetic, stop_crit, pop_size)
+}
+
+// Same as `generate`, `memetic` being applied to every generation once rated.
+// The hall of fame keeps the `HALL_OF_FAME_SIZE` best individuals met, equal
+// ones included: `generate_archived` takes any other, such as
+// `HallOfFame::unique`.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_memetic<T, G, E, S, P, C, M, L, F>(generator: &G, evaluator: &E, selector: &S, mating: &P,
+    crossover: &C, mutation: &M, memetic: &Memetic<L>, stop_crit: &mut F , pop_size: u32) -> RunResult<T>
+where 
+    G: Generator<T>,
+    E: Evaluator<T> + Send + Sync,
+    F: Criterion,
+    S: Selector<T>,
+    P: Mating<T>,
+    C: MultiCrossover<T>,
+    M: Mutation<T>,
+    L: LocalSearch<T> + Sync,
+    T: Clone + Send + Sync {
+
+    let archives = Archives {
+        hall_of_fame: HallOfFame::new(HALL_OF_FAME_SIZE),
+        pareto: None,
+        diversity: None
+    };
+
+    generate_archived(generator, evaluator, selector, mating, crossover, mutation, memetic, archives, stop_crit, pop_size)
+}
+
+// Same as `generate_memetic`, every generation being added to `archives`,
+// which are returned with the result
+#[allow(clippy::too_many_arguments)]
+pub fn generate_archived<T, G, E, S, P, C, M, L, F>(generator: &G, evaluator: &E, selector: &S, mating: &P,
+    crossover: &C, mutation: &M, memetic: &Memetic<L>, mut archives: Archives<T>, stop_crit: &mut F,
+    pop_size: u32) -> RunResult<T>
+where 
+    G: Generator<T>,
+    E: Evaluator<T> + Send + Sync,
+    F: Criterion,
+    S: Selector<T>,
+    P: Mating<T>,
+    C: MultiCrossover<T>,
+    M: Mutation<T>,
+    L: LocalSearch<T> + Sync,
+    T: Clone + Send + Sync {
+
+    // Started before the initial population, its evaluation is part of the run
+    let mut state = RunState::new();
 
     let mut pop = Vec::with_capacity(pop_size as usize);
 
@@ -83,57 +244,61 @@ where
 
     fill_ratings(pop_size, &pop, evaluator, &mut ratings);
 
-    let mut gen = 0;
+    // Best individual ever seen, returned even if the run is cut short
+    let (index, mut best_rating) = best_index(&ratings);
+    let mut best_ever = pop[index].clone();
+
+    state.record(&ratings, pop_size as usize);
+    archives.update(&pop, &ratings, &mut state);
+
+    // Ratings once improved by the local search, to select with. The
+    // individuals keep their own `ratings`, which Baldwinian learning
+    // doesn't change.
+    let mut learned = ratings.clone();
+
     #[cfg(debug_assertions)]
     let mut last_best = 0.0;
 
     // Check if criterion has been reached
-    while !stop_crit.criterion(&ratings) {
-        let parents = selector.selector(&pop, &ratings);
-        let parents_size = parents.len();
-
-        // New generation from the fittest individuals of the previous
-        // population
-        pop.clear();
-        for _ in 0..pop_size {
-            
-            let id1: usize = rng.gen_range(0..parents_size);
-            let mut id2: usize = rng.gen_range(0..parents_size);
-            while id2 == id1 { id2 = rng.gen_range(0..parents_size) };
-
-            // Crossing 2 parents to generate a new element
-            let mut child = crossover.crossover(&parents[id1], &parents[id2]);
-
-            // Chances of mutation happening
-            if rng.gen_range(1..=100) < 25 {
-                // Mutating the new element
-                mutation.mutation(&mut child);
-            }
-            pop.push(child);
-        }
+    while !stop_crit.criterion(&state) {
+        let (next, crossed, mutated) = breed(&pop, &learned, selector, mating, crossover, mutation, pop_size as usize);
+        pop = next;
 
         // Calculate fitness of new generation
         ratings.clear();
         fill_ratings(pop_size, &pop, evaluator, &mut ratings);
-    
-        let (mut best, mut index) = (0.0, 0);
-        ratings.iter().enumerate().for_each(|(i, v)| if *v > best {best = *v; index = i;});
+
+        credit(crossover, mutation, crossed, &mutated, &ratings);
+
+        // Operators are credited for their own children, before the local
+        // search improves them
+        learned.clone_from(&ratings);
+        let searched = memetic.apply(&mut pop, &mut ratings, &mut learned, evaluator);
+
+        let (index, best) = best_index(&ratings);
+        if best > best_rating {
+            best_ever = pop[index].clone();
+            best_rating = best;
+        }
+        state.crossover_stats = crossover.stats();
+        state.mutation_stats = mutation.stats();
+        state.record(&ratings, pop_size as usize + searched);
+        archives.update(&pop, &ratings, &mut state);
 
         //println!("Gen: {gen}. Best rating: {best:.3}");
         //println!("Best element: {}", &pop[index]);
         #[cfg(debug_assertions)]
         {
             if best != last_best {
-                println!("Gen: {gen}. Best rating: {best:.3}");
+                println!("Gen: {}. Best rating: {best:.3}", state.generation);
                 last_best = best;
             }
         }
-
-        gen += 1;
     }
-    
-    let (mut best, mut index) = (0.0, 0);
-    ratings.iter().enumerate().for_each(|(i, v)| if *v > best {best = *v; index = i;});
 
-    (pop.remove(index), gen, best)
+    RunResult {
+        hall_of_fame: archives.hall_of_fame,
+        pareto: archives.pareto,
+        ..RunResult::new(best_ever, best_rating, pop, ratings, state, stop_crit.reason())
+    }
 }
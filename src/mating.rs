@@ -0,0 +1,237 @@
+use rand::{thread_rng, Rng, seq::index::sample};
+
+pub trait Mating<T> {
+    // Picks `count` parents among the selected `pool`, returns their indices
+    // in `pop`. Parents only repeat when the pool is smaller than `count`.
+    fn mating(&self, pop: &[T], ratings: &[f32], pool: &[usize], count: usize) -> Vec<usize>;
+}
+
+pub trait Distance<T> {
+    fn distance(&self, lhs: &T, lhs_rating: f32, rhs: &T, rhs_rating: f32) -> f32;
+}
+
+pub trait Hamming {
+    fn hamming(&self, other: &Self) -> usize;
+}
+
+impl Hamming for String {
+
+    fn hamming(&self, other: &Self) -> usize {
+        let diff = self.chars().zip(other.chars()).filter(|(a, b)| a != b).count();
+
+        diff + self.chars().count().abs_diff(other.chars().count())
+    }
+}
+
+impl<E: PartialEq> Hamming for Vec<E> {
+
+    fn hamming(&self, other: &Self) -> usize {
+        let diff = self.iter().zip(other.iter()).filter(|(a, b)| a != b).count();
+
+        diff + self.len().abs_diff(other.len())
+    }
+}
+
+pub struct FitnessDistance;
+
+impl<T> Distance<T> for FitnessDistance {
+
+    fn distance(&self, _lhs: &T, lhs_rating: f32, _rhs: &T, rhs_rating: f32) -> f32 {
+        (lhs_rating - rhs_rating).abs()
+    }
+}
+
+pub struct HammingDistance;
+
+impl<T: Hamming> Distance<T> for HammingDistance {
+
+    fn distance(&self, lhs: &T, _lhs_rating: f32, rhs: &T, _rhs_rating: f32) -> f32 {
+        lhs.hamming(rhs) as f32
+    }
+}
+
+pub struct Random;
+
+impl<T> Mating<T> for Random {
+
+    fn mating(&self, _pop: &[T], _ratings: &[f32], pool: &[usize], count: usize) -> Vec<usize> {
+        let mut rng = thread_rng();
+
+        if count > pool.len() {
+            return (0..count).map(|_| pool[rng.gen_range(0..pool.len())]).collect();
+        }
+        sample(&mut rng, pool.len(), count).into_iter().map(|i| pool[i]).collect()
+    }
+}
+
+// Random position in a pool of `len` that is not taken yet, or any position
+// once they all are
+fn untaken<R: Rng>(rng: &mut R, len: usize, taken: &[usize]) -> usize {
+    let mut pos = rng.gen_range(0..len);
+    if taken.len() >= len {
+        return pos;
+    }
+
+    while taken.contains(&pos) {
+        pos = rng.gen_range(0..len);
+    }
+    pos
+}
+
+// Fills the remaining slots with the candidate closest to (or farthest from)
+// the first parent
+fn pick_by_distance<T, D>(distance: &D, pop: &[T], ratings: &[f32], pool: &[usize],
+    count: usize, candidates: usize, nearest: bool) -> Vec<usize>
+where
+    D: Distance<T> {
+
+    let mut rng = thread_rng();
+    let mut taken = vec![rng.gen_range(0..pool.len())];
+    let first = pool[taken[0]];
+
+    while taken.len() < count {
+        let mut best = None;
+        let mut best_dist = 0.0;
+
+        for _ in 0..candidates.max(1) {
+            let pos = untaken(&mut rng, pool.len(), &taken);
+
+            let i = pool[pos];
+            let d = distance.distance(&pop[first], ratings[first], &pop[i], ratings[i]);
+            if best.is_none() || (nearest && d < best_dist) || (!nearest && d > best_dist) {
+                best = Some(pos);
+                best_dist = d;
+            }
+        }
+
+        taken.push(best.unwrap());
+    }
+
+    taken.into_iter().map(|pos| pool[pos]).collect()
+}
+
+pub struct Assortative<D> {
+    pub distance: D,
+    pub candidates: usize
+}
+
+impl<T, D: Distance<T>> Mating<T> for Assortative<D> {
+
+    fn mating(&self, pop: &[T], ratings: &[f32], pool: &[usize], count: usize) -> Vec<usize> {
+        pick_by_distance(&self.distance, pop, ratings, pool, count, self.candidates, true)
+    }
+}
+
+pub struct Disassortative<D> {
+    pub distance: D,
+    pub candidates: usize
+}
+
+impl<T, D: Distance<T>> Mating<T> for Disassortative<D> {
+
+    fn mating(&self, pop: &[T], ratings: &[f32], pool: &[usize], count: usize) -> Vec<usize> {
+        pick_by_distance(&self.distance, pop, ratings, pool, count, self.candidates, false)
+    }
+}
+
+// CHC style: a partner is only accepted if its Hamming distance to every
+// already chosen parent is above `threshold`. After `attempts` failures the
+// most distant candidate seen is used instead.
+pub struct IncestPrevention {
+    pub threshold: usize,
+    pub attempts: usize
+}
+
+impl<T: Hamming> Mating<T> for IncestPrevention {
+
+    fn mating(&self, pop: &[T], _ratings: &[f32], pool: &[usize], count: usize) -> Vec<usize> {
+        let mut rng = thread_rng();
+        let mut taken = vec![rng.gen_range(0..pool.len())];
+
+        while taken.len() < count {
+            let (mut fallback, mut fallback_dist) = (None, 0);
+
+            for _ in 0..self.attempts.max(1) {
+                let pos = untaken(&mut rng, pool.len(), &taken);
+
+                let dist = taken.iter()
+                    .map(|t| pop[pool[*t]].hamming(&pop[pool[pos]]))
+                    .min()
+                    .unwrap();
+                if dist > self.threshold {
+                    fallback = Some(pos);
+                    break;
+                }
+                if fallback.is_none() || dist > fallback_dist {
+                    fallback = Some(pos);
+                    fallback_dist = dist;
+                }
+            }
+
+            taken.push(fallback.unwrap());
+        }
+
+        taken.into_iter().map(|pos| pool[pos]).collect()
+    }
+}
+
+#[test]
+fn test_assortative_fitness() {
+    let mating = Assortative{ distance: FitnessDistance, candidates: 64 };
+
+    let pop = vec![0, 1, 2];
+    let ratings = vec![1.0, 1.1, 50.0];
+
+    // With enough candidates the closest rating always wins
+    for _ in 0..20 {
+        let result = mating.mating(&pop, &ratings, &[0, 1, 2], 2);
+        if result[0] != 2 {
+            assert_ne!(result[1], 2);
+        }
+    }
+}
+
+#[test]
+fn test_incest_prevention() {
+    let mating = IncestPrevention{ threshold: 2, attempts: 50 };
+
+    let pop = vec![String::from("aaaa"), String::from("aaab"), String::from("bbbb")];
+    let ratings = vec![1.0, 1.0, 1.0];
+
+    for _ in 0..20 {
+        let result = mating.mating(&pop, &ratings, &[0, 1, 2], 2);
+        assert!(result.contains(&2));
+    }
+}
+
+#[test]
+fn test_random() {
+    let pop = vec![0, 1, 2, 3];
+    let ratings = vec![1.0; 4];
+
+    let mut result = Random.mating(&pop, &ratings, &[0, 2, 3], 3);
+    result.sort();
+    assert_eq!(result, vec![0, 2, 3]);
+
+    // Pool smaller than the parents needed, as with Elitism{ max_pop: 1 }
+    assert_eq!(Random.mating(&pop, &ratings, &[1], 2), vec![1, 1]);
+}
+
+#[test]
+fn test_disassortative() {
+    let mating = Disassortative{ distance: FitnessDistance, candidates: 64 };
+
+    let pop = vec![0, 1, 2];
+    let ratings = vec![1.0, 1.1, 50.0];
+
+    for _ in 0..20 {
+        let result = mating.mating(&pop, &ratings, &[0, 1, 2], 2);
+        if result[0] != 2 {
+            assert_eq!(result[1], 2);
+        }
+    }
+
+    assert_eq!(mating.mating(&pop, &ratings, &[1], 2), vec![1, 1]);
+    let incest = IncestPrevention{ threshold: 2, attempts: 5 };
+    assert_eq!(incest.mating(&[String::from("a")], &[1.0], &[0], 3), vec![0, 0, 0]);
+}
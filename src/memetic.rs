@@ -0,0 +1,347 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::{thread_rng, Rng, seq::SliceRandom};
+
+use crate::anneal::{accept, Cooling};
+use crate::evaluator::Evaluator;
+use crate::mutation::Mutation;
+use crate::NUM_THREADS;
+
+// Improves `pop` in place, spending at most `budget` calls to the evaluator.
+// `rating` is the current rating of `pop`, the rating of the result is
+// returned.
+pub trait LocalSearch<T> {
+    fn local_search(&self, pop: &mut T, rating: f32, evaluator: &dyn Evaluator<T>, budget: usize) -> f32;
+}
+
+// Candidate moves around a genome, closures returning them work too
+pub trait Neighbourhood<T> {
+    fn neighbours(&self, pop: &T) -> Vec<T>;
+}
+
+impl<T, F: Fn(&T) -> Vec<T>> Neighbourhood<T> for F {
+
+    fn neighbours(&self, pop: &T) -> Vec<T> {
+        self(pop)
+    }
+}
+
+// Moves to the first better neighbour found, neighbours are tried in random
+// order, until none is better or the budget is spent
+pub struct FirstImprovement<N> {
+    pub neighbourhood: N
+}
+
+impl<T, N: Neighbourhood<T>> LocalSearch<T> for FirstImprovement<N>
+{
+    fn local_search(&self, pop: &mut T, rating: f32, evaluator: &dyn Evaluator<T>, budget: usize) -> f32 {
+        let (mut rating, mut budget) = (rating, budget);
+
+        'search: while budget > 0 {
+            let mut neighbours = self.neighbourhood.neighbours(pop);
+            neighbours.shuffle(&mut thread_rng());
+
+            for neighbour in neighbours {
+                if budget == 0 {
+                    break 'search;
+                }
+                budget -= 1;
+
+                let r = evaluator.evaluator(&neighbour);
+                if r > rating {
+                    *pop = neighbour;
+                    rating = r;
+                    continue 'search;
+                }
+            }
+            break;
+        }
+
+        rating
+    }
+}
+
+// Rates the whole neighbourhood and moves to its best if it is better, until
+// none is better or the budget is spent
+pub struct BestImprovement<N> {
+    pub neighbourhood: N
+}
+
+impl<T, N: Neighbourhood<T>> LocalSearch<T> for BestImprovement<N>
+{
+    fn local_search(&self, pop: &mut T, rating: f32, evaluator: &dyn Evaluator<T>, budget: usize) -> f32 {
+        let (mut rating, mut budget) = (rating, budget);
+
+        while budget > 0 {
+            let mut best = None;
+            for neighbour in self.neighbourhood.neighbours(pop) {
+                if budget == 0 {
+                    break;
+                }
+                budget -= 1;
+
+                let r = evaluator.evaluator(&neighbour);
+                if r > best.as_ref().map_or(rating, |(_, best)| *best) {
+                    best = Some((neighbour, r));
+                }
+            }
+
+            match best {
+                Some((neighbour, r)) => {
+                    *pop = neighbour;
+                    rating = r;
+                },
+                None => break
+            }
+        }
+
+        rating
+    }
+}
+
+// Simulated annealing with `mutation` as the move, accepting moves as
+// `anneal` does. The schedule restarts at step 0 for every search, the best
+// genome met is kept.
+pub struct Annealing<M> {
+    pub mutation: M,
+    pub cooling: Cooling
+}
+
+impl<T: Clone, M: Mutation<T>> LocalSearch<T> for Annealing<M>
+{
+    fn local_search(&self, pop: &mut T, rating: f32, evaluator: &dyn Evaluator<T>, budget: usize) -> f32 {
+        let mut rng = thread_rng();
+        let (mut current, mut current_rating) = (pop.clone(), rating);
+        let mut best_rating = rating;
+
+        for step in 0..budget {
+            let mut candidate = current.clone();
+            self.mutation.mutation(&mut candidate);
+            let r = evaluator.evaluator(&candidate);
+            self.mutation.credit(&[r - current_rating]);
+
+            let delta = (r - current_rating) as f64;
+            if accept(&mut rng, delta, self.cooling.temperature(step)) {
+                current = candidate;
+                current_rating = r;
+                if current_rating > best_rating {
+                    pop.clone_from(&current);
+                    best_rating = current_rating;
+                }
+            }
+        }
+
+        best_rating
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Inheritance {
+    // The improved genome replaces the individual
+    Lamarckian,
+    // The individual keeps its genome and rating, only the rating used for
+    // selection is improved
+    Baldwinian
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    // Every new child, with the given probability
+    Offspring,
+    // The given number of best individuals of each generation, with the
+    // given probability
+    Elites(usize)
+}
+
+// Counts the calls made by the local searches
+struct Counting<'a, E> {
+    evaluator: &'a E,
+    calls: AtomicUsize
+}
+
+impl<T, E: Evaluator<T>> Evaluator<T> for Counting<'_, E> {
+
+    fn evaluator(&self, pop: &T) -> f32 {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.evaluator.evaluator(pop)
+    }
+}
+
+// Local search applied each generation, once the new population is rated
+pub struct Memetic<L> {
+    pub local_search: L,
+    pub probability: f64,
+    // Evaluations allowed to each local search
+    pub budget: usize,
+    pub target: Target,
+    pub inheritance: Inheritance
+}
+
+impl<L> Memetic<L> {
+
+    // Runs the local searches, in parallel, and returns the evaluations they
+    // used. `learned` starts as a copy of `ratings` and gets the improved
+    // ratings, to select with. In Lamarckian mode the improved genomes and
+    // ratings also replace the ones of `pop`, in Baldwinian mode `pop` and
+    // `ratings` are left as they are so every genome keeps its own rating.
+    pub fn apply<T, E>(&self, pop: &mut [T], ratings: &mut [f32], learned: &mut [f32], evaluator: &E) -> usize
+    where
+        L: LocalSearch<T> + Sync,
+        E: Evaluator<T> + Send + Sync,
+        T: Clone + Send {
+
+        if self.probability <= 0.0 || self.budget == 0 {
+            return 0;
+        }
+
+        let mut candidates: Vec<usize> = (0..pop.len()).collect();
+        if let Target::Elites(count) = self.target {
+            candidates.sort_by(|a, b| ratings[*b].total_cmp(&ratings[*a]));
+            candidates.truncate(count);
+        }
+        let mut rng = thread_rng();
+        candidates.retain(|_| rng.gen_bool(self.probability.min(1.0)));
+        if candidates.is_empty() {
+            return 0;
+        }
+
+        let mut jobs: Vec<(usize, T, f32)> = candidates.iter().map(|i| (*i, pop[*i].clone(), ratings[*i])).collect();
+        let chunk = jobs.len().div_ceil(NUM_THREADS as usize);
+        let evaluator = &Counting{ evaluator, calls: AtomicUsize::new(0) };
+        std::thread::scope(|scope| {
+            for jobs in jobs.chunks_mut(chunk) {
+                scope.spawn(move || {
+                    for (_, genome, rating) in jobs {
+                        *rating = self.local_search.local_search(genome, *rating, evaluator, self.budget);
+                    }
+                });
+            }
+        });
+
+        for (i, genome, rating) in jobs {
+            // Searches never return a worse individual than they started from
+            if rating > ratings[i] {
+                learned[i] = rating;
+                if self.inheritance == Inheritance::Lamarckian {
+                    ratings[i] = rating;
+                    pop[i] = genome;
+                }
+            }
+        }
+
+        evaluator.calls.load(Ordering::Relaxed)
+    }
+}
+
+// Local search doing nothing, for runs without one
+pub struct NoLocalSearch;
+
+impl<T> LocalSearch<T> for NoLocalSearch
+{
+    fn local_search(&self, _pop: &mut T, rating: f32, _evaluator: &dyn Evaluator<T>, _budget: usize) -> f32 {
+        rating
+    }
+}
+
+#[cfg(test)]
+struct CountOnes;
+
+#[cfg(test)]
+impl Evaluator<Vec<bool>> for CountOnes {
+
+    fn evaluator(&self, pop: &Vec<bool>) -> f32 {
+        pop.iter().filter(|b| **b).count() as f32
+    }
+}
+
+#[cfg(test)]
+fn flips(pop: &[bool]) -> Vec<Vec<bool>> {
+    (0..pop.len()).map(|i| {
+        let mut neighbour = pop.to_vec();
+        neighbour[i] = !neighbour[i];
+        neighbour
+    }).collect()
+}
+
+#[test]
+fn test_hill_climbing() {
+    let searches: [Box<dyn LocalSearch<Vec<bool>>>; 2] = [
+        Box::new(FirstImprovement{ neighbourhood: |pop: &Vec<bool>| flips(pop) }),
+        Box::new(BestImprovement{ neighbourhood: |pop: &Vec<bool>| flips(pop) })
+    ];
+
+    for search in searches {
+        let mut pop = vec![false; 8];
+        let rating = search.local_search(&mut pop, 0.0, &CountOnes, 1000);
+        assert_eq!(rating, 8.0);
+        assert_eq!(pop, vec![true; 8]);
+
+        // The budget is never exceeded
+        let mut pop = vec![false; 8];
+        assert!(search.local_search(&mut pop, 0.0, &CountOnes, 3) <= 3.0);
+    }
+}
+
+#[test]
+fn test_inheritance() {
+    let memetic = |inheritance| Memetic {
+        local_search: BestImprovement{ neighbourhood: |pop: &Vec<bool>| flips(pop) },
+        probability: 1.0,
+        budget: 100,
+        target: Target::Elites(1),
+        inheritance
+    };
+
+    let mut pop = vec![vec![false; 4], vec![true, false, false, false]];
+    let (mut ratings, mut learned) = (vec![0.0, 1.0], vec![0.0, 1.0]);
+    memetic(Inheritance::Baldwinian).apply(&mut pop, &mut ratings, &mut learned, &CountOnes);
+    assert_eq!((ratings, learned), (vec![0.0, 1.0], vec![0.0, 4.0]));
+    assert_eq!(pop[1], vec![true, false, false, false]);
+
+    let (mut ratings, mut learned) = (vec![0.0, 1.0], vec![0.0, 1.0]);
+    memetic(Inheritance::Lamarckian).apply(&mut pop, &mut ratings, &mut learned, &CountOnes);
+    assert_eq!((ratings, learned), (vec![0.0, 4.0], vec![0.0, 4.0]));
+    assert_eq!(pop[1], vec![true; 4]);
+}
+
+#[test]
+fn test_baldwinian_run() {
+    use crate::criterion::Iterations;
+    use crate::genome::bits::{BitFlip, BitGenome, OneMax, OnePoint, RandomBits};
+    use crate::mating::Random;
+    use crate::selector::Tournament;
+
+    let memetic = Memetic {
+        local_search: FirstImprovement{ neighbourhood: |pop: &BitGenome| (0..pop.len()).map(|i| {
+            let mut neighbour = pop.clone();
+            neighbour.flip(i);
+            neighbour
+        }).collect::<Vec<_>>() },
+        probability: 1.0,
+        budget: 8,
+        target: Target::Offspring,
+        inheritance: Inheritance::Baldwinian
+    };
+    let result = crate::generate_memetic(&RandomBits{ len: 32 }, &OneMax, &Tournament{ max_pop: 20 }, &Random,
+        &OnePoint, &BitFlip{ rate: 1.0 / 32.0 }, &memetic, &mut Iterations::new(10), 30);
+
+    // Learned ratings only steer the selection, every kept genome has its own
+    assert_eq!(OneMax.evaluator(&result.best), result.rating);
+    for (pop, rating) in result.population.iter().zip(&result.ratings) {
+        assert_eq!(OneMax.evaluator(pop), *rating);
+    }
+    for (pop, rating) in result.hall_of_fame.entries() {
+        assert_eq!(OneMax.evaluator(pop), *rating);
+    }
+}
+
+#[test]
+fn test_annealing() {
+    use crate::genome::bits::{BitFlip, BitGenome, OneMax};
+
+    let search = Annealing{ mutation: BitFlip{ rate: 1.0 / 16.0 }, cooling: Cooling::Geometric{ start: 1.0, alpha: 0.99 } };
+    let mut pop = BitGenome::new(16);
+    let rating = search.local_search(&mut pop, 0.0, &OneMax, 2000);
+    assert_eq!(rating, 16.0);
+    assert_eq!(pop.count_ones(), 16);
+}
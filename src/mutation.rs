@@ -1,4 +1,102 @@
+use rand::{thread_rng, Rng, distributions::WeightedIndex, prelude::Distribution};
+
+use crate::state::OperatorStats;
 
 pub trait Mutation<T> {
     fn mutation(&self, pop: &mut T) ;
-}
\ No newline at end of file
+
+    // Shown in the stats of adaptive operators, the type name by default
+    fn name(&self) -> String {
+        crate::short_type_name::<Self>()
+    }
+
+    // How much each mutated child improved on the best rating of its parents,
+    // in the order the mutations were made. Only adaptive operators care.
+    fn credit(&self, _improvements: &[f32]) {}
+
+    fn stats(&self) -> Vec<OperatorStats> {
+        vec![]
+    }
+}
+
+// Applies one of the mutations, picked according to its weight, with the
+// same rules on weights as `crossover::OneOf`
+pub struct OneOf<T> {
+    operators: Vec<Box<dyn Mutation<T>>>,
+    weights: WeightedIndex<f64>
+}
+
+impl<T> OneOf<T> {
+
+    pub fn new(operators: Vec<(f64, Box<dyn Mutation<T>>)>) -> Self {
+        let (weights, operators): (Vec<f64>, Vec<_>) = operators.into_iter().unzip();
+
+        Self {
+            operators,
+            weights: WeightedIndex::new(weights).expect("OneOf needs valid weights, with at least one above 0")
+        }
+    }
+}
+
+impl<T> Mutation<T> for OneOf<T>
+{
+    fn mutation(&self, pop: &mut T) {
+        self.operators[self.weights.sample(&mut thread_rng())].mutation(pop);
+    }
+}
+
+// Applies every mutation, in order
+pub struct Sequence<T>(pub Vec<Box<dyn Mutation<T>>>);
+
+impl<T> Mutation<T> for Sequence<T>
+{
+    fn mutation(&self, pop: &mut T) {
+        for m in &self.0 {
+            m.mutation(pop);
+        }
+    }
+}
+
+pub struct WithProbability<M>(pub f64, pub M);
+
+impl<T, M: Mutation<T>> Mutation<T> for WithProbability<M>
+{
+    fn mutation(&self, pop: &mut T) {
+        if thread_rng().gen_bool(self.0) {
+            self.1.mutation(pop);
+        }
+    }
+}
+
+pub struct Repeat<M>(pub usize, pub M);
+
+impl<T, M: Mutation<T>> Mutation<T> for Repeat<M>
+{
+    fn mutation(&self, pop: &mut T) {
+        for _ in 0..self.0 {
+            self.1.mutation(pop);
+        }
+    }
+}
+
+#[test]
+fn test_combinators() {
+    struct Increment(i32);
+
+    impl Mutation<i32> for Increment
+    {
+        fn mutation(&self, pop: &mut i32) {
+            *pop += self.0;
+        }
+    }
+
+    let mutation = Sequence(vec![
+        Box::new(Repeat(3, Increment(1))),
+        Box::new(WithProbability(0.0, Increment(100))),
+        Box::new(OneOf::new(vec![(1.0, Box::new(Increment(10))), (0.0, Box::new(Increment(1000)))]))
+    ]);
+
+    let mut pop = 0;
+    mutation.mutation(&mut pop);
+    assert_eq!(pop, 13);
+}
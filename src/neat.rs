@@ -0,0 +1,841 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use rand::{thread_rng, Rng, seq::SliceRandom};
+
+use crate::criterion::Criterion;
+use crate::crossover::Crossover;
+use crate::evaluator::Evaluator;
+use crate::generator::Generator;
+use crate::genome::real::standard_normal;
+use crate::mutation::Mutation;
+use crate::result::RunResult;
+use crate::state::RunState;
+use crate::{best_index, fill_ratings};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Input,
+    // Always outputs 1
+    Bias,
+    Output,
+    Hidden
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeGene {
+    pub id: usize,
+    pub kind: NodeKind
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectionGene {
+    pub innovation: usize,
+    pub from: usize,
+    pub to: usize,
+    pub weight: f64,
+    pub enabled: bool
+}
+
+// Network encoding of NEAT (Stanley and Miikkulainen). Connections are kept
+// sorted by innovation number, so that genes of two genomes can be aligned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Genome {
+    pub nodes: Vec<NodeGene>,
+    pub connections: Vec<ConnectionGene>
+}
+
+impl Genome {
+
+    fn has_node(&self, id: usize) -> bool {
+        self.nodes.iter().any(|n| n.id == id)
+    }
+
+    fn insert(&mut self, connection: ConnectionGene) {
+        let pos = self.connections.partition_point(|c| c.innovation < connection.innovation);
+        self.connections.insert(pos, connection);
+    }
+
+    // Whether `to` leads to `from` through enabled connections, i.e. if a
+    // connection from -> to would close a cycle
+    fn reaches(&self, to: usize, from: usize) -> bool {
+        let mut seen = HashSet::new();
+        let mut stack = vec![to];
+
+        while let Some(node) = stack.pop() {
+            if node == from {
+                return true;
+            }
+            if seen.insert(node) {
+                stack.extend(self.connections.iter().filter(|c| c.enabled && c.from == node).map(|c| c.to));
+            }
+        }
+
+        false
+    }
+}
+
+struct InnovationState {
+    next_node: usize,
+    next_innovation: usize,
+    connections: HashMap<(usize, usize), usize>,
+    // Connection innovation -> new node and innovations of its two connections
+    splits: HashMap<usize, (usize, usize, usize)>
+}
+
+// Innovation numbers of a run, the same structural change gets the same
+// numbers in every genome it happens to
+pub struct Innovations {
+    state: Mutex<InnovationState>
+}
+
+impl Innovations {
+
+    // Node ids of the initial genomes: inputs, then the bias, then outputs
+    pub fn new(inputs: usize, outputs: usize) -> Self {
+        Self {
+            state: Mutex::new(InnovationState {
+                next_node: inputs + 1 + outputs,
+                next_innovation: 0,
+                connections: HashMap::new(),
+                splits: HashMap::new()
+            })
+        }
+    }
+
+    pub fn connection(&self, from: usize, to: usize) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let next = state.next_innovation;
+
+        let innovation = *state.connections.entry((from, to)).or_insert(next);
+        if innovation == next {
+            state.next_innovation += 1;
+        }
+
+        innovation
+    }
+
+    // Splitting the connection `innovation` gives the new node and the
+    // innovations of the connections to and from it
+    pub fn split(&self, innovation: usize, from: usize, to: usize) -> (usize, usize, usize) {
+        let known = self.state.lock().unwrap().splits.get(&innovation).copied();
+        if let Some(split) = known {
+            return split;
+        }
+
+        let node = {
+            let mut state = self.state.lock().unwrap();
+            state.next_node += 1;
+            state.next_node - 1
+        };
+        let split = (node, self.connection(from, node), self.connection(node, to));
+        // Another thread may have split it meanwhile, the first one wins
+        *self.state.lock().unwrap().splits.entry(innovation).or_insert(split)
+    }
+}
+
+// Minimal genomes: every input and the bias connected to every output
+pub struct RandomNetwork {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub innovations: Arc<Innovations>
+}
+
+impl Generator<Genome> for RandomNetwork {
+
+    fn generator(&self) -> Genome {
+        let mut rng = thread_rng();
+        let kind = |id: usize| match id {
+            id if id < self.inputs => NodeKind::Input,
+            id if id == self.inputs => NodeKind::Bias,
+            _ => NodeKind::Output
+        };
+        let nodes: Vec<NodeGene> = (0..self.inputs + 1 + self.outputs).map(|id| NodeGene{ id, kind: kind(id) }).collect();
+
+        let mut genome = Genome{ nodes, connections: vec![] };
+        for from in 0..=self.inputs {
+            for to in self.inputs + 1..self.inputs + 1 + self.outputs {
+                genome.insert(ConnectionGene {
+                    innovation: self.innovations.connection(from, to),
+                    from,
+                    to,
+                    weight: rng.gen_range(-1.0..1.0),
+                    enabled: true
+                });
+            }
+        }
+
+        genome
+    }
+}
+
+// Splits a random enabled connection with a new hidden node. The connection
+// in gets weight 1 and the one out the old weight, so the network first
+// behaves about the same.
+pub struct AddNode {
+    pub innovations: Arc<Innovations>
+}
+
+impl Mutation<Genome> for AddNode
+{
+    fn mutation(&self, pop: &mut Genome) {
+        let enabled: Vec<usize> = (0..pop.connections.len()).filter(|i| pop.connections[*i].enabled).collect();
+        let Some(&i) = enabled.choose(&mut thread_rng()) else {
+            return;
+        };
+
+        let ConnectionGene{ innovation, from, to, weight, .. } = pop.connections[i];
+        let (node, innovation_in, innovation_out) = self.innovations.split(innovation, from, to);
+        // Already split in this genome, through a parent
+        if pop.has_node(node) {
+            return;
+        }
+
+        pop.connections[i].enabled = false;
+        pop.nodes.push(NodeGene{ id: node, kind: NodeKind::Hidden });
+        pop.insert(ConnectionGene{ innovation: innovation_in, from, to: node, weight: 1.0, enabled: true });
+        pop.insert(ConnectionGene{ innovation: innovation_out, from: node, to, weight, enabled: true });
+    }
+}
+
+// Connects two unconnected nodes with a random weight, never into an input
+// or the bias. Unless `recurrent`, connections closing a cycle are not made.
+pub struct AddConnection {
+    pub innovations: Arc<Innovations>,
+    pub recurrent: bool,
+    // Random pairs tried before giving up
+    pub attempts: usize
+}
+
+impl Mutation<Genome> for AddConnection
+{
+    fn mutation(&self, pop: &mut Genome) {
+        let mut rng = thread_rng();
+
+        for _ in 0..self.attempts {
+            let from = pop.nodes.choose(&mut rng).unwrap();
+            let to = pop.nodes.choose(&mut rng).unwrap();
+            if matches!(to.kind, NodeKind::Input | NodeKind::Bias) {
+                continue;
+            }
+            let (from, to) = (from.id, to.id);
+            if pop.connections.iter().any(|c| c.from == from && c.to == to) {
+                continue;
+            }
+            if !self.recurrent && (from == to || pop.reaches(to, from)) {
+                continue;
+            }
+
+            pop.insert(ConnectionGene {
+                innovation: self.innovations.connection(from, to),
+                from,
+                to,
+                weight: rng.gen_range(-1.0..1.0),
+                enabled: true
+            });
+            return;
+        }
+    }
+}
+
+// Each weight is perturbed with probability `rate` by a gaussian step of
+// `power`, or drawn again in -1..1 with probability `reset`
+pub struct WeightMutation {
+    pub rate: f64,
+    pub power: f64,
+    pub reset: f64
+}
+
+impl Mutation<Genome> for WeightMutation
+{
+    fn mutation(&self, pop: &mut Genome) {
+        let mut rng = thread_rng();
+
+        for c in pop.connections.iter_mut() {
+            if rng.gen_bool(self.rate) {
+                c.weight += self.power * standard_normal(&mut rng);
+            } else if rng.gen_bool(self.reset) {
+                c.weight = rng.gen_range(-1.0..1.0);
+            }
+        }
+    }
+}
+
+// Genes are aligned by innovation number: matching genes are inherited from
+// either parent, disjoint and excess genes from `parent1`, which must be the
+// fitter one. A gene disabled in either parent stays disabled with
+// probability `disabled`. Unless `recurrent`, genes closing a cycle are not
+// enabled back.
+#[derive(Clone, Copy, Debug)]
+pub struct NeatCrossover {
+    pub disabled: f64,
+    pub recurrent: bool
+}
+
+impl Crossover<Genome> for NeatCrossover
+{
+    fn crossover(&self, parent1: &Genome, parent2: &Genome) -> Genome {
+        let mut rng = thread_rng();
+        let other: HashMap<usize, &ConnectionGene> = parent2.connections.iter().map(|c| (c.innovation, c)).collect();
+
+        let mut child = Genome{ nodes: parent1.nodes.clone(), connections: vec![] };
+        for gene in &parent1.connections {
+            let gene = match other.get(&gene.innovation) {
+                Some(matching) => {
+                    let mut inherited = if rng.gen_bool(0.5) { gene.clone() } else { (*matching).clone() };
+                    inherited.enabled = !((!gene.enabled || !matching.enabled) && rng.gen_bool(self.disabled));
+                    inherited
+                },
+                None => gene.clone()
+            };
+            child.connections.push(gene);
+        }
+
+        // Genes enabled back must not close a cycle, the structure of
+        // `parent1` is kept otherwise
+        if self.recurrent {
+            return child;
+        }
+        for (i, gene) in parent1.connections.iter().enumerate() {
+            if child.connections[i].enabled && !gene.enabled {
+                child.connections[i].enabled = false;
+                child.connections[i].enabled = !child.reaches(gene.to, gene.from);
+            }
+        }
+
+        child
+    }
+
+    // Which parent is fitter is not known here, the runner makes sure of it
+    fn crossover_pair(&self, parent1: &Genome, parent2: &Genome) -> (Genome, Genome) {
+        (self.crossover(parent1, parent2), self.crossover(parent1, parent2))
+    }
+}
+
+// delta = c1 * E / N + c2 * D / N + c3 * W, E and D being the excess and
+// disjoint genes, W the mean weight difference of matching genes and N the
+// gene count of the larger genome (1 below 20 genes, as in the paper)
+#[derive(Clone, Copy, Debug)]
+pub struct Compatibility {
+    pub c1: f64,
+    pub c2: f64,
+    pub c3: f64
+}
+
+impl Compatibility {
+
+    pub fn distance(&self, lhs: &Genome, rhs: &Genome) -> f64 {
+        let (mut i, mut j) = (0, 0);
+        let (mut disjoint, mut matching, mut weights) = (0, 0, 0.0);
+        let (a, b) = (&lhs.connections, &rhs.connections);
+
+        while i < a.len() && j < b.len() {
+            match a[i].innovation.cmp(&b[j].innovation) {
+                std::cmp::Ordering::Equal => {
+                    matching += 1;
+                    weights += (a[i].weight - b[j].weight).abs();
+                    i += 1;
+                    j += 1;
+                },
+                std::cmp::Ordering::Less => { disjoint += 1; i += 1; },
+                std::cmp::Ordering::Greater => { disjoint += 1; j += 1; }
+            }
+        }
+        let excess = (a.len() - i) + (b.len() - j);
+
+        let n = a.len().max(b.len());
+        let n = if n < 20 { 1.0 } else { n as f64 };
+        let w = if matching > 0 { weights / matching as f64 } else { 0.0 };
+
+        self.c1 * excess as f64 / n + self.c2 * disjoint as f64 / n + self.c3 * w
+    }
+}
+
+// Sigmoid steepened as in the NEAT paper
+pub fn steepened_sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-4.9 * x).exp())
+}
+
+// Network of the enabled connections of a genome, without cycles
+pub struct FeedForward {
+    inputs: Vec<usize>,
+    bias: Option<usize>,
+    outputs: Vec<usize>,
+    // Non input nodes in evaluation order, with their incoming connections
+    order: Vec<(usize, Vec<(usize, f64)>)>,
+    values: Vec<f64>,
+    activation: fn(f64) -> f64
+}
+
+fn incoming(genome: &Genome, index: &HashMap<usize, usize>) -> Vec<Vec<(usize, f64)>> {
+    let mut incoming = vec![vec![]; genome.nodes.len()];
+    for c in genome.connections.iter().filter(|c| c.enabled) {
+        incoming[index[&c.to]].push((index[&c.from], c.weight));
+    }
+
+    incoming
+}
+
+fn roles(genome: &Genome) -> (Vec<usize>, Option<usize>, Vec<usize>) {
+    let of_kind = |kind| (0..genome.nodes.len()).filter(|i| genome.nodes[*i].kind == kind).collect::<Vec<_>>();
+
+    (of_kind(NodeKind::Input), of_kind(NodeKind::Bias).first().copied(), of_kind(NodeKind::Output))
+}
+
+impl FeedForward {
+
+    pub fn new(genome: &Genome, activation: fn(f64) -> f64) -> Self {
+        let index: HashMap<usize, usize> = genome.nodes.iter().enumerate().map(|(i, n)| (n.id, i)).collect();
+        let incoming = incoming(genome, &index);
+        let (inputs, bias, outputs) = roles(genome);
+
+        // Kahn's topological sort
+        let mut pending: Vec<usize> = incoming.iter().map(|i| i.len()).collect();
+        let mut ready: VecDeque<usize> = (0..genome.nodes.len()).filter(|i| pending[*i] == 0).collect();
+        let mut order = vec![];
+        while let Some(node) = ready.pop_front() {
+            if !matches!(genome.nodes[node].kind, NodeKind::Input | NodeKind::Bias) {
+                order.push((node, incoming[node].clone()));
+            }
+            for c in genome.connections.iter().filter(|c| c.enabled && index[&c.from] == node) {
+                let to = index[&c.to];
+                pending[to] -= 1;
+                if pending[to] == 0 {
+                    ready.push_back(to);
+                }
+            }
+        }
+
+        Self {
+            inputs,
+            bias,
+            outputs,
+            order,
+            values: vec![0.0; genome.nodes.len()],
+            activation
+        }
+    }
+
+    pub fn activate(&mut self, inputs: &[f64]) -> Vec<f64> {
+        self.values.iter_mut().for_each(|v| *v = 0.0);
+        for (node, x) in self.inputs.iter().zip(inputs) {
+            self.values[*node] = *x;
+        }
+        if let Some(bias) = self.bias {
+            self.values[bias] = 1.0;
+        }
+
+        for (node, incoming) in &self.order {
+            let sum: f64 = incoming.iter().map(|(from, w)| w * self.values[*from]).sum();
+            self.values[*node] = (self.activation)(sum);
+        }
+
+        self.outputs.iter().map(|o| self.values[*o]).collect()
+    }
+}
+
+// Network of the enabled connections of a genome, cycles included. Each
+// activation is one step: nodes read the values of the previous step, so a
+// signal takes as many steps as connections to go through.
+pub struct Recurrent {
+    inputs: Vec<usize>,
+    bias: Option<usize>,
+    outputs: Vec<usize>,
+    incoming: Vec<Vec<(usize, f64)>>,
+    values: Vec<f64>,
+    activation: fn(f64) -> f64
+}
+
+impl Recurrent {
+
+    pub fn new(genome: &Genome, activation: fn(f64) -> f64) -> Self {
+        let index: HashMap<usize, usize> = genome.nodes.iter().enumerate().map(|(i, n)| (n.id, i)).collect();
+        let (inputs, bias, outputs) = roles(genome);
+
+        Self {
+            inputs,
+            bias,
+            outputs,
+            incoming: incoming(genome, &index),
+            values: vec![0.0; genome.nodes.len()],
+            activation
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.values.iter_mut().for_each(|v| *v = 0.0);
+    }
+
+    pub fn activate(&mut self, inputs: &[f64]) -> Vec<f64> {
+        for (node, x) in self.inputs.iter().zip(inputs) {
+            self.values[*node] = *x;
+        }
+        if let Some(bias) = self.bias {
+            self.values[bias] = 1.0;
+        }
+
+        let previous = self.values.clone();
+        for (node, incoming) in self.incoming.iter().enumerate() {
+            if !incoming.is_empty() {
+                let sum: f64 = incoming.iter().map(|(from, w)| w * previous[*from]).sum();
+                self.values[node] = (self.activation)(sum);
+            }
+        }
+
+        self.outputs.iter().map(|o| self.values[*o]).collect()
+    }
+}
+
+// Rates genomes by running `evaluate` on their feed-forward network
+pub struct FeedForwardEvaluator<F> {
+    pub activation: fn(f64) -> f64,
+    pub evaluate: F
+}
+
+impl<F: Fn(&mut FeedForward) -> f32> Evaluator<Genome> for FeedForwardEvaluator<F> {
+
+    fn evaluator(&self, pop: &Genome) -> f32 {
+        (self.evaluate)(&mut FeedForward::new(pop, self.activation))
+    }
+}
+
+// Rates genomes by running `evaluate` on their recurrent network
+pub struct RecurrentEvaluator<F> {
+    pub activation: fn(f64) -> f64,
+    pub evaluate: F
+}
+
+impl<F: Fn(&mut Recurrent) -> f32> Evaluator<Genome> for RecurrentEvaluator<F> {
+
+    fn evaluator(&self, pop: &Genome) -> f32 {
+        (self.evaluate)(&mut Recurrent::new(pop, self.activation))
+    }
+}
+
+// Parameters of a NEAT run
+pub struct Neat {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub compatibility: Compatibility,
+    // Genomes closer than this to the representative of a species join it
+    pub threshold: f64,
+    // Chances of each mutation for a child
+    pub weight_mutation: f64,
+    pub add_node: f64,
+    pub add_connection: f64,
+    pub weights: WeightMutation,
+    pub crossover: NeatCrossover,
+    // Chances of a child having two parents
+    pub crossover_rate: f64,
+    // Fraction of each species allowed to reproduce
+    pub survival: f64,
+    // Generations without improvement before a species is dropped, but for
+    // the `elite_species` best ones
+    pub max_staleness: usize,
+    pub elite_species: usize,
+    // Allow cycles, for `Recurrent` networks
+    pub recurrent: bool,
+    pub innovations: Arc<Innovations>
+}
+
+impl Neat {
+
+    // Parameters of the NEAT paper
+    pub fn new(inputs: usize, outputs: usize) -> Self {
+        Self {
+            inputs,
+            outputs,
+            compatibility: Compatibility{ c1: 1.0, c2: 1.0, c3: 0.4 },
+            threshold: 3.0,
+            weight_mutation: 0.8,
+            add_node: 0.03,
+            add_connection: 0.05,
+            weights: WeightMutation{ rate: 0.9, power: 2.5, reset: 0.1 },
+            crossover: NeatCrossover{ disabled: 0.75, recurrent: false },
+            crossover_rate: 0.75,
+            survival: 0.2,
+            max_staleness: 15,
+            elite_species: 2,
+            recurrent: false,
+            innovations: Arc::new(Innovations::new(inputs, outputs))
+        }
+    }
+}
+
+struct Species {
+    representative: Genome,
+    members: Vec<usize>,
+    best: f32,
+    staleness: usize
+}
+
+// Groups genomes by compatibility with the representatives of the previous
+// generation, new species are made for the others
+fn speciate(neat: &Neat, species: &mut Vec<Species>, pop: &[Genome]) {
+    species.iter_mut().for_each(|s| s.members.clear());
+
+    for (i, genome) in pop.iter().enumerate() {
+        let found = species.iter_mut()
+            .find(|s| neat.compatibility.distance(&s.representative, genome) < neat.threshold);
+        match found {
+            Some(s) => s.members.push(i),
+            None => species.push(Species {
+                representative: genome.clone(),
+                members: vec![i],
+                best: f32::MIN,
+                staleness: 0
+            })
+        }
+    }
+    species.retain(|s| !s.members.is_empty());
+}
+
+// Offspring of each species, proportional to the sum of its shared fitness
+// (ratings shifted to be positive and divided by the species size), which
+// is the mean shifted rating of the species
+fn allot(species: &[Species], ratings: &[f32], pop_size: usize) -> Vec<usize> {
+    let min = ratings.iter().copied().fold(f32::MAX, f32::min) as f64;
+    let shared: Vec<f64> = species.iter()
+        .map(|s| s.members.iter().map(|i| ratings[*i] as f64 - min + 1e-6).sum::<f64>() / s.members.len() as f64)
+        .collect();
+    let total: f64 = shared.iter().sum();
+
+    let mut counts: Vec<usize> = shared.iter().map(|f| (f / total * pop_size as f64).floor() as usize).collect();
+    // Remaining places to the species with the largest shares
+    let mut order: Vec<usize> = (0..species.len()).collect();
+    order.sort_by(|a, b| shared[*b].total_cmp(&shared[*a]));
+    let mut left = pop_size - counts.iter().sum::<usize>();
+    for i in order.iter().cycle() {
+        if left == 0 {
+            break;
+        }
+        counts[*i] += 1;
+        left -= 1;
+    }
+
+    counts
+}
+
+// Evolves network genomes with NEAT: speciation, explicit fitness sharing,
+// structural mutations and aligned crossover. Returns the best genome met.
+pub fn neuroevolution<E, F>(neat: &Neat, evaluator: &E, stop_crit: &mut F, pop_size: u32) -> RunResult<Genome>
+where
+    E: Evaluator<Genome> + Send + Sync,
+    F: Criterion {
+
+    assert!(pop_size > 0, "neuroevolution needs at least one genome");
+
+    let mut rng = thread_rng();
+    let generator = RandomNetwork{ inputs: neat.inputs, outputs: neat.outputs, innovations: neat.innovations.clone() };
+    let add_node = AddNode{ innovations: neat.innovations.clone() };
+    let add_connection = AddConnection{ innovations: neat.innovations.clone(), recurrent: neat.recurrent, attempts: 20 };
+    let crossover = NeatCrossover{ recurrent: neat.recurrent, ..neat.crossover };
+
+    let mut state = RunState::new();
+
+    let mut pop: Vec<Genome> = (0..pop_size).map(|_| generator.generator()).collect();
+    let mut ratings = Vec::with_capacity(pop_size as usize);
+    fill_ratings(pop_size, &pop, evaluator, &mut ratings);
+
+    let (index, mut best_rating) = best_index(&ratings);
+    let mut best_ever = pop[index].clone();
+
+    state.record(&ratings, pop_size as usize);
+
+    let mut species = vec![];
+    while !stop_crit.criterion(&state) {
+        speciate(neat, &mut species, &pop);
+
+        for s in species.iter_mut() {
+            let top = s.members.iter().map(|i| ratings[*i]).fold(f32::MIN, f32::max);
+            if top > s.best {
+                s.best = top;
+                s.staleness = 0;
+            } else {
+                s.staleness += 1;
+            }
+            s.members.sort_by(|a, b| ratings[*b].total_cmp(&ratings[*a]));
+        }
+        // The best species are kept whatever their staleness, the others
+        // would otherwise all go on long plateaus. The best one always is,
+        // for the population not to die out.
+        species.sort_by(|a, b| b.best.total_cmp(&a.best));
+        let mut rank = 0;
+        species.retain(|s| {
+            rank += 1;
+            rank <= neat.elite_species.max(1) || s.staleness <= neat.max_staleness
+        });
+
+        let counts = allot(&species, &ratings, pop_size as usize);
+        let mut next = Vec::with_capacity(pop_size as usize);
+        for (s, count) in species.iter().zip(counts) {
+            if count == 0 {
+                continue;
+            }
+            // The champion of large enough species is kept as is
+            let mut count = count;
+            if s.members.len() >= 5 {
+                next.push(pop[s.members[0]].clone());
+                count -= 1;
+            }
+
+            let parents = &s.members[..((s.members.len() as f64 * neat.survival).ceil() as usize).max(1)];
+            for _ in 0..count {
+                let first = *parents.choose(&mut rng).unwrap();
+                let mut child = if parents.len() > 1 && rng.gen_bool(neat.crossover_rate) {
+                    let second = *parents.choose(&mut rng).unwrap();
+                    let (fitter, other) = if ratings[first] >= ratings[second] { (first, second) } else { (second, first) };
+                    crossover.crossover(&pop[fitter], &pop[other])
+                } else {
+                    pop[first].clone()
+                };
+
+                if rng.gen_bool(neat.weight_mutation) {
+                    neat.weights.mutation(&mut child);
+                }
+                if rng.gen_bool(neat.add_node) {
+                    add_node.mutation(&mut child);
+                }
+                if rng.gen_bool(neat.add_connection) {
+                    add_connection.mutation(&mut child);
+                }
+                next.push(child);
+            }
+        }
+
+        // Representatives of the next generation, from the current members
+        for s in species.iter_mut() {
+            s.representative = pop[*s.members.choose(&mut rng).unwrap()].clone();
+        }
+
+        pop = next;
+        ratings.clear();
+        fill_ratings(pop_size, &pop, evaluator, &mut ratings);
+
+        let (index, best) = best_index(&ratings);
+        if best > best_rating {
+            best_ever = pop[index].clone();
+            best_rating = best;
+        }
+        state.record(&ratings, pop_size as usize);
+    }
+
+    RunResult::new(best_ever, best_rating, pop, ratings, state, stop_crit.reason())
+}
+
+#[test]
+fn test_structural_mutations() {
+    let innovations = Arc::new(Innovations::new(2, 1));
+    let generator = RandomNetwork{ inputs: 2, outputs: 1, innovations: innovations.clone() };
+    let add_node = AddNode{ innovations: innovations.clone() };
+    let add_connection = AddConnection{ innovations: innovations.clone(), recurrent: false, attempts: 50 };
+
+    let mut genome = generator.generator();
+    genome.connections.iter_mut().for_each(|c| c.weight = 1.0);
+    let before = FeedForward::new(&genome, |x| x).activate(&[0.5, 0.25]);
+    assert_eq!(before, vec![1.75]);
+
+    // Same split in another genome gives the same innovations
+    let mut other = genome.clone();
+    add_node.mutation(&mut genome);
+    let split = genome.connections.iter().find(|c| !c.enabled).unwrap().innovation;
+    while !other.connections.iter().any(|c| c.innovation == split && !c.enabled) {
+        other = generator.generator();
+        add_node.mutation(&mut other);
+    }
+    assert_eq!(genome.connections.iter().map(|c| c.innovation).collect::<Vec<_>>(),
+        other.connections.iter().map(|c| c.innovation).collect::<Vec<_>>());
+
+    // Identity activation, the split keeps the output
+    assert_eq!(FeedForward::new(&genome, |x| x).activate(&[0.5, 0.25]), before);
+
+    for _ in 0..20 {
+        add_node.mutation(&mut genome);
+        add_connection.mutation(&mut genome);
+    }
+    assert!(genome.connections.iter().filter(|c| c.enabled).all(|c| !genome.reaches(c.to, c.from)));
+    assert!(genome.connections.windows(2).all(|w| w[0].innovation < w[1].innovation));
+}
+
+#[test]
+fn test_crossover_and_distance() {
+    let innovations = Arc::new(Innovations::new(1, 1));
+    let generator = RandomNetwork{ inputs: 1, outputs: 1, innovations: innovations.clone() };
+    let add_node = AddNode{ innovations };
+    let compatibility = Compatibility{ c1: 1.0, c2: 1.0, c3: 0.0 };
+
+    let fitter = generator.generator();
+    let mut other = fitter.clone();
+    add_node.mutation(&mut other);
+    // One connection disabled in `other`, two excess ones
+    assert_eq!(compatibility.distance(&fitter, &other), 2.0);
+    assert_eq!(compatibility.distance(&fitter, &fitter), 0.0);
+
+    // Excess genes come from the fitter parent only
+    let crossover = NeatCrossover{ disabled: 0.75, recurrent: false };
+    let child = crossover.crossover(&fitter, &other);
+    assert_eq!(child.connections.len(), fitter.connections.len());
+    let child = crossover.crossover(&other, &fitter);
+    assert_eq!(child.connections.len(), other.connections.len());
+}
+
+#[test]
+fn test_recurrent() {
+    // in -> out, out -> out
+    let genome = Genome {
+        nodes: vec![NodeGene{ id: 0, kind: NodeKind::Input }, NodeGene{ id: 1, kind: NodeKind::Output }],
+        connections: vec![
+            ConnectionGene{ innovation: 0, from: 0, to: 1, weight: 1.0, enabled: true },
+            ConnectionGene{ innovation: 1, from: 1, to: 1, weight: 1.0, enabled: true }
+        ]
+    };
+    let mut network = Recurrent::new(&genome, |x| x);
+
+    assert_eq!(network.activate(&[1.0]), vec![1.0]);
+    assert_eq!(network.activate(&[1.0]), vec![2.0]);
+    network.reset();
+    assert_eq!(network.activate(&[0.0]), vec![0.0]);
+}
+
+#[test]
+fn test_xor() {
+    use crate::criterion::{Iterations, Mark};
+
+    const CASES: [([f64; 2], f64); 4] = [([0.0, 0.0], 0.0), ([0.0, 1.0], 1.0), ([1.0, 0.0], 1.0), ([1.0, 1.0], 0.0)];
+    let evaluator = FeedForwardEvaluator {
+        activation: steepened_sigmoid,
+        evaluate: |network: &mut FeedForward| {
+            4.0 - CASES.iter().map(|(inputs, output)| (network.activate(inputs)[0] - output).powi(2)).sum::<f64>() as f32
+        }
+    };
+
+    let mut stop_crit = Mark{ max_rating: 3.9 }.or(Iterations::new(1000));
+    let result = neuroevolution(&Neat::new(2, 1), &evaluator, &mut stop_crit, 150);
+
+    let mut network = FeedForward::new(&result.best, steepened_sigmoid);
+    for (inputs, output) in CASES {
+        assert!((network.activate(&inputs)[0] - output).abs() < 0.5, "{inputs:?} misclassified, rating {}", result.rating);
+    }
+}
+
+#[test]
+fn test_stale_species() {
+    use crate::criterion::Iterations;
+
+    // Nothing ever improves, every species goes stale and none is elite
+    let neat = Neat{ elite_species: 0, max_staleness: 0, ..Neat::new(2, 1) };
+    let evaluator = FeedForwardEvaluator{ activation: steepened_sigmoid, evaluate: |_: &mut FeedForward| 1.0 };
+    let result = neuroevolution(&neat, &evaluator, &mut Iterations::new(5), 20);
+
+    assert_eq!(result.generations, 5);
+    assert_eq!(result.population.len(), 20);
+}
+
+#[test]
+#[should_panic(expected = "at least one genome")]
+fn test_empty_population() {
+    use crate::criterion::Iterations;
+
+    let evaluator = FeedForwardEvaluator{ activation: steepened_sigmoid, evaluate: |_: &mut FeedForward| 1.0 };
+    neuroevolution(&Neat::new(2, 1), &evaluator, &mut Iterations::new(5), 0);
+}
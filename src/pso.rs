@@ -0,0 +1,185 @@
+use rand::{thread_rng, Rng};
+
+use crate::criterion::Criterion;
+use crate::evaluator::Evaluator;
+use crate::generator::Generator;
+use crate::genome::real::Bounds;
+use crate::result::RunResult;
+use crate::state::RunState;
+use crate::{best_index, fill_ratings};
+
+// Whose best position attracts each particle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Topology {
+    // gbest: the best position found by the whole swarm
+    Global,
+    // lbest: the best among the particle and its `neighbours` closest
+    // particles on each side of a ring
+    Ring { neighbours: usize }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Velocity {
+    // v = w * v + c1 * r1 * (personal - x) + c2 * r2 * (neighbourhood - x)
+    Inertia { w: f64, c1: f64, c2: f64 },
+    // Clerc and Kennedy: v = chi * (v + c1 * r1 * (personal - x) + c2 * r2 *
+    // (neighbourhood - x)), chi derived from phi = c1 + c2 which must be over
+    // 4 (2.05 each is the usual choice)
+    Constriction { c1: f64, c2: f64 }
+}
+
+impl Velocity {
+
+    // Inertia and attraction weights
+    fn coefficients(&self) -> (f64, f64, f64) {
+        match *self {
+            Velocity::Inertia { w, c1, c2 } => (w, c1, c2),
+            Velocity::Constriction { c1, c2 } => {
+                let phi = c1 + c2;
+                assert!(phi > 4.0, "constriction needs c1 + c2 over 4, got {phi}");
+                let chi = 2.0 / (2.0 - phi - (phi * phi - 4.0 * phi).sqrt()).abs();
+                (chi, chi * c1, chi * c2)
+            }
+        }
+    }
+}
+
+// Index of the best personal position in the neighbourhood of each particle
+fn neighbourhood_bests(topology: Topology, ratings: &[f32]) -> Vec<usize> {
+    let len = ratings.len();
+    match topology {
+        Topology::Global => vec![best_index(ratings).0; len],
+        Topology::Ring { neighbours } => {
+            let k = neighbours.min(len.saturating_sub(1) / 2);
+            (0..len).map(|i| (0..=2 * k)
+                .map(|offset| (i + len + offset - k) % len)
+                .max_by(|a, b| ratings[*a].total_cmp(&ratings[*b]))
+                .unwrap())
+                .collect()
+        }
+    }
+}
+
+// Particle swarm optimization over real genomes, for the same generators and
+// evaluators as the GA. Particles start at generated positions with a
+// velocity of half the way to another generated one. Each velocity component
+// is kept within `v_max` times the width of its bounds, if given, and
+// positions out of `bounds` are repaired.
+#[allow(clippy::too_many_arguments)]
+pub fn particle_swarm<G, E, F>(generator: &G, evaluator: &E, stop_crit: &mut F, bounds: &Bounds,
+    topology: Topology, velocity: Velocity, v_max: Option<f64>, swarm_size: u32) -> RunResult<Vec<f64>>
+where
+    G: Generator<Vec<f64>>,
+    E: Evaluator<Vec<f64>> + Send + Sync,
+    F: Criterion {
+
+    assert!(swarm_size > 0, "particle swarm needs at least one particle");
+
+    let mut rng = thread_rng();
+
+    let mut state = RunState::new();
+
+    let mut positions: Vec<Vec<f64>> = (0..swarm_size).map(|_| generator.generator()).collect();
+    let mut velocities: Vec<Vec<f64>> = positions.iter()
+        .map(|x| generator.generator().iter().zip(x).map(|(y, x)| (y - x) / 2.0).collect())
+        .collect();
+
+    let mut ratings = Vec::with_capacity(swarm_size as usize);
+    fill_ratings(swarm_size, &positions, evaluator, &mut ratings);
+
+    // Best position found by each particle
+    let mut personal = positions.clone();
+    let mut personal_ratings = ratings.clone();
+
+    state.record(&ratings, swarm_size as usize);
+
+    let (w, c1, c2) = velocity.coefficients();
+    while !stop_crit.criterion(&state) {
+        let attractors = neighbourhood_bests(topology, &personal_ratings);
+        for i in 0..positions.len() {
+            let attractor = &personal[attractors[i]];
+            let (x, v) = (&mut positions[i], &mut velocities[i]);
+
+            for j in 0..x.len() {
+                let (r1, r2): (f64, f64) = (rng.gen(), rng.gen());
+                v[j] = w * v[j] + c1 * r1 * (personal[i][j] - x[j]) + c2 * r2 * (attractor[j] - x[j]);
+                if let Some(v_max) = v_max {
+                    let limit = v_max * bounds.width(j);
+                    v[j] = v[j].clamp(-limit, limit);
+                }
+                x[j] += v[j];
+            }
+            bounds.repair(x);
+        }
+
+        ratings.clear();
+        fill_ratings(swarm_size, &positions, evaluator, &mut ratings);
+
+        for (i, rating) in ratings.iter().enumerate() {
+            if *rating > personal_ratings[i] {
+                personal[i].clone_from(&positions[i]);
+                personal_ratings[i] = *rating;
+            }
+        }
+        state.record(&ratings, swarm_size as usize);
+    }
+
+    let (index, best) = best_index(&personal_ratings);
+    let best_ever = personal[index].clone();
+
+    RunResult::new(best_ever, best, positions, ratings, state, stop_crit.reason())
+}
+
+#[test]
+fn test_ring() {
+    let ratings = [5.0, 1.0, 2.0, 3.0, 0.0, 4.0];
+
+    let bests = neighbourhood_bests(Topology::Ring{ neighbours: 1 }, &ratings);
+    assert_eq!((bests[2], bests[4]), (3, 5));
+    // Wraps around
+    assert_eq!(bests[1], 0);
+    assert_eq!(neighbourhood_bests(Topology::Global, &ratings), vec![0; 6]);
+    assert!(neighbourhood_bests(Topology::Ring{ neighbours: 2 }, &[]).is_empty());
+}
+
+#[test]
+fn test_constriction() {
+    let (chi, c1, c2) = Velocity::Constriction{ c1: 2.05, c2: 2.05 }.coefficients();
+    assert!((chi - 0.7298).abs() < 1e-4);
+    assert!((c1 - chi * 2.05).abs() < 1e-12 && c1 == c2);
+}
+
+#[test]
+#[should_panic(expected = "over 4")]
+fn test_constriction_phi_too_low() {
+    Velocity::Constriction{ c1: 1.5, c2: 1.5 }.coefficients();
+}
+
+#[test]
+fn test_particle_swarm() {
+    use crate::criterion::{Iterations, Mark};
+    use crate::genome::real::{Repair, UniformReal};
+
+    struct Sphere;
+
+    impl Evaluator<Vec<f64>> for Sphere {
+
+        fn evaluator(&self, pop: &Vec<f64>) -> f32 {
+            -pop.iter().map(|x| (x - 1.0).powi(2)).sum::<f64>() as f32
+        }
+    }
+
+    let bounds = Bounds::uniform(5, -5.0, 5.0, Repair::Clamp);
+    let generator = UniformReal{ bounds: bounds.clone() };
+    let setups = [
+        (Topology::Global, Velocity::Inertia{ w: 0.7298, c1: 1.49618, c2: 1.49618 }, Some(0.5)),
+        (Topology::Ring{ neighbours: 1 }, Velocity::Constriction{ c1: 2.05, c2: 2.05 }, None)
+    ];
+
+    for (topology, velocity, v_max) in setups {
+        let mut stop_crit = Mark{ max_rating: -1e-6 }.or(Iterations::new(2000));
+        let result = particle_swarm(&generator, &Sphere, &mut stop_crit, &bounds, topology, velocity, v_max, 30);
+
+        assert!(result.rating >= -1e-6, "{topology:?} {velocity:?}: {}", result.rating);
+    }
+}
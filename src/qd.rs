@@ -0,0 +1,360 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use rand::{thread_rng, Rng, seq::index::sample};
+
+use crate::criterion::Criterion;
+use crate::crossover::MultiCrossover;
+use crate::evaluator::Evaluator;
+use crate::generator::Generator;
+use crate::mutation::Mutation;
+use crate::selector::Selector;
+use crate::result::RunResult;
+use crate::state::RunState;
+use crate::{credit, fill_ratings, Crossed, NUM_THREADS};
+
+// What an individual does, as a point in a space where distances make sense
+// (final position of a robot, its path...), independently of how well
+pub trait Descriptor<T> {
+    fn descriptor(&self, pop: &T) -> Vec<f64>;
+}
+
+fn describe<T, D>(pop: &[T], descriptor: &D) -> Vec<Vec<f64>>
+where
+    D: Descriptor<T> + Sync,
+    T: Sync {
+
+    if pop.is_empty() {
+        return vec![];
+    }
+
+    let chunk = pop.len().div_ceil(NUM_THREADS as usize);
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = pop.chunks(chunk)
+            .map(|pop| scope.spawn(move || pop.iter().map(|p| descriptor.descriptor(p)).collect::<Vec<_>>()))
+            .collect();
+
+        threads.into_iter().flat_map(|t| t.join().expect("Thread did not close correctly")).collect()
+    })
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f64>().sqrt()
+}
+
+// Mean distance of `point` to its k nearest neighbours among `others`
+pub fn novelty(point: &[f64], others: &[&[f64]], k: usize) -> f64 {
+    let mut distances: Vec<f64> = others.iter().map(|o| distance(point, o)).collect();
+    let k = k.min(distances.len());
+    if k == 0 {
+        return 0.0;
+    }
+
+    distances.select_nth_unstable_by(k - 1, f64::total_cmp);
+    distances[..k].iter().sum::<f64>() / k as f64
+}
+
+// Novelty search (Lehman and Stanley): `selector` picks from the novelty of
+// the individuals instead of their rating, the novelty being the mean
+// distance to the k nearest descriptors of the population and of an archive
+// of past ones. Descriptors more novel than `threshold` join the archive, the
+// others with probability `add_probability`. Past `capacity` descriptors,
+// the oldest ones leave the archive. Ratings are still the ones of the
+// evaluator, for the criterion and the best individual of the run.
+pub struct NoveltySelector<S, D> {
+    pub selector: S,
+    pub descriptor: D,
+    pub k: usize,
+    pub threshold: f64,
+    pub add_probability: f64,
+    pub capacity: usize,
+    archive: Mutex<VecDeque<Vec<f64>>>
+}
+
+impl<S, D> NoveltySelector<S, D> {
+
+    pub fn new(selector: S, descriptor: D, k: usize, threshold: f64, add_probability: f64, capacity: usize) -> Self {
+        Self {
+            selector,
+            descriptor,
+            k,
+            threshold,
+            add_probability,
+            capacity,
+            archive: Mutex::new(VecDeque::new())
+        }
+    }
+
+    pub fn archive(&self) -> Vec<Vec<f64>> {
+        self.archive.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl<T, S, D> Selector<T> for NoveltySelector<S, D>
+where
+    S: Selector<T>,
+    D: Descriptor<T> + Sync,
+    T: Sync {
+
+    // Without the individuals there is nothing to compare, `selector` uses
+    // the ratings
+    fn select(&self, ratings: &[f32]) -> Vec<usize> {
+        self.selector.select(ratings)
+    }
+
+    fn select_from(&self, pop: &[T], _ratings: &[f32]) -> Vec<usize> {
+        let descriptors = describe(pop, &self.descriptor);
+        let mut archive = self.archive.lock().unwrap();
+        let mut rng = thread_rng();
+
+        let scores: Vec<f32> = descriptors.iter().enumerate().map(|(i, d)| {
+            let others: Vec<&[f64]> = descriptors.iter().enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, o)| o.as_slice())
+                .chain(archive.iter().map(|a| a.as_slice()))
+                .collect();
+            novelty(d, &others, self.k) as f32
+        }).collect();
+
+        for (d, score) in descriptors.iter().zip(&scores) {
+            if *score as f64 > self.threshold || rng.gen_bool(self.add_probability) {
+                archive.push_back(d.clone());
+            }
+        }
+        while archive.len() > self.capacity {
+            archive.pop_front();
+        }
+
+        self.selector.select_from(pop, &scores)
+    }
+}
+
+// Feature dimension of a MAP-Elites grid, `bins` cells over min..max. Values
+// out of the range go to the first or last cell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Feature {
+    pub min: f64,
+    pub max: f64,
+    pub bins: usize
+}
+
+// Archive of MAP-Elites (Mouret and Clune), the best individual met for each
+// cell of the grid, and its rating
+pub struct Grid<T> {
+    pub features: Vec<Feature>,
+    pub cells: Vec<Option<(T, f32)>>,
+    // Coverage and QD-score after each generation
+    pub coverage_history: Vec<f64>,
+    pub qd_history: Vec<f64>
+}
+
+impl<T> Grid<T> {
+
+    pub fn new(features: Vec<Feature>) -> Self {
+        let cells = features.iter().map(|f| f.bins.max(1)).product();
+
+        Self {
+            features,
+            cells: (0..cells).map(|_| None).collect(),
+            coverage_history: vec![],
+            qd_history: vec![]
+        }
+    }
+
+    pub fn cell(&self, descriptor: &[f64]) -> usize {
+        self.features.iter().zip(descriptor).fold(0, |index, (f, x)| {
+            let bins = f.bins.max(1);
+            let t = (x - f.min) / (f.max - f.min);
+            index * bins + ((t * bins as f64).floor().max(0.0) as usize).min(bins - 1)
+        })
+    }
+
+    // Puts `pop` in its cell if empty or held by a worse individual
+    pub fn insert(&mut self, pop: T, rating: f32, descriptor: &[f64]) -> bool {
+        let cell = self.cell(descriptor);
+        match &self.cells[cell] {
+            Some((_, elite)) if *elite >= rating => false,
+            _ => {
+                self.cells[cell] = Some((pop, rating));
+                true
+            }
+        }
+    }
+
+    pub fn elites(&self) -> impl Iterator<Item = &(T, f32)> {
+        self.cells.iter().flatten()
+    }
+
+    // Fraction of the cells filled
+    pub fn coverage(&self) -> f64 {
+        self.elites().count() as f64 / self.cells.len() as f64
+    }
+
+    // Sum of the ratings of the elites, to compare runs they should be
+    // positive
+    pub fn qd_score(&self) -> f64 {
+        self.elites().map(|(_, r)| *r as f64).sum()
+    }
+
+    pub fn best(&self) -> Option<&(T, f32)> {
+        self.elites().max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+// MAP-Elites: starts from `initial` generated individuals, then each
+// generation makes `batch` children from random elites (as many as
+// `crossover` takes) and mutates them. Every individual is kept in `grid`
+// if it beats the elite of its cell. Returns the best elite, the elites
+// being the population of the result.
+#[allow(clippy::too_many_arguments)]
+pub fn map_elites<T, G, E, D, C, M, F>(generator: &G, evaluator: &E, descriptor: &D, crossover: &C, mutation: &M,
+    stop_crit: &mut F, grid: &mut Grid<T>, initial: u32, batch: u32) -> RunResult<T>
+where
+    G: Generator<T>,
+    E: Evaluator<T> + Send + Sync,
+    D: Descriptor<T> + Sync,
+    C: MultiCrossover<T>,
+    M: Mutation<T>,
+    F: Criterion,
+    T: Clone + Send + Sync {
+
+    assert!(initial > 0 && batch > 0, "MAP-Elites needs at least one initial individual and one child per batch");
+
+    let mut rng = thread_rng();
+    let mut state = RunState::new();
+
+    let mut pop: Vec<T> = (0..initial).map(|_| generator.generator()).collect();
+    let (mut crossed, mut mutated): (Crossed, _) = (vec![], vec![]);
+    loop {
+        let mut ratings = Vec::with_capacity(pop.len());
+        fill_ratings(pop.len() as u32, &pop, evaluator, &mut ratings);
+        credit(crossover, mutation, std::mem::take(&mut crossed), &mutated, &ratings);
+        mutated.clear();
+        state.crossover_stats = crossover.stats();
+        state.mutation_stats = mutation.stats();
+        let descriptors = describe(&pop, descriptor);
+
+        for ((p, rating), d) in pop.into_iter().zip(&ratings).zip(&descriptors) {
+            grid.insert(p, *rating, d);
+        }
+        grid.coverage_history.push(grid.coverage());
+        grid.qd_history.push(grid.qd_score());
+        state.record(&ratings, ratings.len());
+
+        if stop_crit.criterion(&state) {
+            break;
+        }
+
+        let elites: Vec<&(T, f32)> = grid.elites().collect();
+        let parents = crossover.parents().min(elites.len());
+        pop = Vec::with_capacity(batch as usize);
+        while pop.len() < batch as usize {
+            let mates = sample(&mut rng, elites.len(), parents);
+            let parent_rating = mates.iter().map(|i| elites[i].1).fold(f32::MIN, f32::max);
+            let mates: Vec<&T> = mates.into_iter().map(|i| &elites[i].0).collect();
+            let first = pop.len();
+
+            let children = crossover.multi_crossover(&mates);
+            assert!(!children.is_empty(), "crossover gave no child, the batch could never be filled");
+            for mut child in children {
+                if pop.len() == batch as usize {
+                    break;
+                }
+                mutation.mutation(&mut child);
+                mutated.push((parent_rating, pop.len()));
+                pop.push(child);
+            }
+            crossed.push((parent_rating, first..pop.len()));
+        }
+    }
+
+    let (best, rating) = grid.best().cloned().expect("no individual was generated");
+    let (elites, ratings) = grid.elites().cloned().unzip();
+
+    RunResult::new(best, rating, elites, ratings, state, stop_crit.reason())
+}
+
+#[cfg(test)]
+struct Position;
+
+#[cfg(test)]
+impl Descriptor<Vec<f64>> for Position {
+
+    fn descriptor(&self, pop: &Vec<f64>) -> Vec<f64> {
+        pop.clone()
+    }
+}
+
+#[cfg(test)]
+struct Centered;
+
+#[cfg(test)]
+impl Evaluator<Vec<f64>> for Centered {
+
+    fn evaluator(&self, pop: &Vec<f64>) -> f32 {
+        10.0 - pop.iter().map(|x| x * x).sum::<f64>() as f32
+    }
+}
+
+#[test]
+fn test_novelty() {
+    let others: [&[f64]; 3] = [&[1.0, 0.0], &[3.0, 0.0], &[0.0, 10.0]];
+
+    assert_eq!(novelty(&[0.0, 0.0], &others, 2), 2.0);
+    assert_eq!(novelty(&[0.0, 0.0], &others[..1], 5), 1.0);
+
+    struct Identity;
+
+    impl Descriptor<f64> for Identity {
+
+        fn descriptor(&self, pop: &f64) -> Vec<f64> {
+            vec![*pop]
+        }
+    }
+
+    // The isolated individual is the most novel, whatever its rating
+    let selector = NoveltySelector::new(crate::selector::Elitism{ max_pop: 1 }, Identity, 2, 5.0, 0.0, 2);
+    assert_eq!(selector.select_from(&[0.0, 0.1, 0.2, 9.0], &[4.0, 3.0, 2.0, 1.0]), vec![3]);
+    assert_eq!(selector.archive(), vec![vec![9.0]]);
+
+    // Only the latest descriptors are kept
+    selector.select_from(&[20.0, 40.0], &[0.0, 0.0]);
+    assert_eq!(selector.archive(), vec![vec![20.0], vec![40.0]]);
+}
+
+#[test]
+fn test_map_elites() {
+    use crate::genome::real::{Bounds, BlxAlpha, Repair, UniformReal, Gaussian, SigmaSchedule};
+    use crate::criterion::Iterations;
+
+    let bounds = Bounds::uniform(2, -1.0, 1.0, Repair::Clamp);
+    let feature = Feature{ min: -1.0, max: 1.0, bins: 4 };
+    let mut grid = Grid::new(vec![feature, feature]);
+    assert_eq!(grid.cell(&[-1.0, -5.0]), 0);
+    assert_eq!(grid.cell(&[0.1, 1.0]), 2 * 4 + 3);
+
+    let mutation = Gaussian::new(SigmaSchedule::Constant(0.2), 1.0, bounds.clone());
+    let crossover = BlxAlpha{ alpha: 0.5, bounds: bounds.clone() };
+    let result = map_elites(&UniformReal{ bounds }, &Centered, &Position, &crossover,
+        &mutation, &mut Iterations::new(300), &mut grid, 20, 20);
+
+    assert_eq!(grid.coverage(), 1.0);
+    assert!(result.rating > 9.9);
+    assert_eq!(grid.coverage_history.len(), 301);
+    assert!(grid.qd_history.windows(2).all(|w| w[0] <= w[1]));
+    // Corner cells hold at worst (1, 1)
+    assert!(grid.elites().all(|(_, r)| *r >= 8.0));
+}
+
+#[test]
+#[should_panic(expected = "at least one initial individual")]
+fn test_no_initial() {
+    use crate::genome::real::{Bounds, Repair, UniformReal, Gaussian, SigmaSchedule, WholeArithmetic};
+    use crate::criterion::Iterations;
+
+    let bounds = Bounds::uniform(2, -1.0, 1.0, Repair::Clamp);
+    let feature = Feature{ min: -1.0, max: 1.0, bins: 4 };
+    let mutation = Gaussian::new(SigmaSchedule::Constant(0.2), 1.0, bounds.clone());
+    map_elites(&UniformReal{ bounds }, &Centered, &Position, &WholeArithmetic{ alpha: 0.5 }, &mutation,
+        &mut Iterations::new(1), &mut Grid::new(vec![feature, feature]), 0, 20);
+}
@@ -0,0 +1,290 @@
+use std::time::Duration;
+
+use crate::state::{Diversity, RunState};
+use crate::NUM_THREADS;
+
+// Best individuals of a whole run, in decreasing rating order. Individuals
+// that left the population are kept, so a good one met mid-run is not lost.
+pub struct HallOfFame<T> {
+    pub capacity: usize,
+    entries: Vec<(T, f32)>,
+    // Genomes considered the same, only the first one met is kept
+    same: Option<fn(&T, &T) -> bool>
+}
+
+impl<T> HallOfFame<T> {
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: vec![],
+            same: None
+        }
+    }
+
+    // Keeps a single copy of equal genomes
+    pub fn unique(capacity: usize) -> Self
+    where
+        T: PartialEq {
+
+        Self {
+            capacity,
+            entries: vec![],
+            same: Some(T::eq)
+        }
+    }
+
+    pub fn update(&mut self, pop: &[T], ratings: &[f32])
+    where
+        T: Clone {
+
+        for (p, rating) in pop.iter().zip(ratings) {
+            if self.entries.len() == self.capacity && self.entries.last().is_none_or(|(_, r)| r >= rating) {
+                continue;
+            }
+            if let Some(same) = self.same {
+                if self.entries.iter().any(|(e, _)| same(e, p)) {
+                    continue;
+                }
+            }
+
+            let pos = self.entries.partition_point(|(_, r)| r >= rating);
+            self.entries.insert(pos, (p.clone(), *rating));
+            self.entries.truncate(self.capacity);
+        }
+    }
+
+    pub fn entries(&self) -> &[(T, f32)] {
+        &self.entries
+    }
+
+    pub fn best(&self) -> Option<&(T, f32)> {
+        self.entries.first()
+    }
+}
+
+// Several values to maximize for each individual, for the Pareto archive
+pub trait Objectives<T> {
+    fn objectives(&self, pop: &T) -> Vec<f64>;
+}
+
+// `a` is at least as good as `b` on every objective and better on one
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x >= y) && a.iter().zip(b).any(|(x, y)| x > y)
+}
+
+// Non-dominated individuals met during a run, with their objectives. They
+// are computed in parallel for every individual of every generation, on top
+// of the evaluator and not counted in the evaluations of the run. The front
+// is not bounded, with many objectives most individuals can end up in it.
+pub struct ParetoArchive<T> {
+    objectives: Box<dyn Objectives<T> + Send + Sync>,
+    entries: Vec<(T, Vec<f64>)>
+}
+
+impl<T> ParetoArchive<T> {
+
+    pub fn new<O: Objectives<T> + Send + Sync + 'static>(objectives: O) -> Self {
+        Self {
+            objectives: Box::new(objectives),
+            entries: vec![]
+        }
+    }
+
+    pub fn update(&mut self, pop: &[T])
+    where
+        T: Clone + Sync {
+
+        if pop.is_empty() {
+            return;
+        }
+
+        let chunk = pop.len().div_ceil(NUM_THREADS as usize);
+        let objectives = &self.objectives;
+        let values: Vec<Vec<f64>> = std::thread::scope(|scope| {
+            let threads: Vec<_> = pop.chunks(chunk)
+                .map(|pop| scope.spawn(move || pop.iter().map(|p| objectives.objectives(p)).collect::<Vec<_>>()))
+                .collect();
+
+            threads.into_iter().flat_map(|t| t.join().expect("Thread did not close correctly")).collect()
+        });
+
+        for (p, values) in pop.iter().zip(values) {
+            // Dominated, or already in with the same values
+            if self.entries.iter().any(|(_, e)| dominates(e, &values) || *e == values) {
+                continue;
+            }
+
+            self.entries.retain(|(_, e)| !dominates(&values, e));
+            self.entries.push((p.clone(), values));
+        }
+    }
+
+    pub fn entries(&self) -> &[(T, Vec<f64>)] {
+        &self.entries
+    }
+}
+
+// What `generate_archived` keeps track of across the run. `diversity`
+// measures each generation into `RunState::diversity_history`, for criteria
+// such as `LowDiversity`.
+pub struct Archives<T> {
+    pub hall_of_fame: HallOfFame<T>,
+    pub pareto: Option<ParetoArchive<T>>,
+    pub diversity: Option<Box<dyn Diversity<T> + Send + Sync>>
+}
+
+impl<T> Archives<T> {
+
+    // Called once the generation is recorded in `state`
+    pub fn update(&mut self, pop: &[T], ratings: &[f32], state: &mut RunState)
+    where
+        T: Clone + Sync {
+
+        self.hall_of_fame.update(pop, ratings);
+        if let Some(pareto) = self.pareto.as_mut() {
+            pareto.update(pop);
+        }
+        if let Some(diversity) = &self.diversity {
+            state.diversity_history.push(diversity.diversity(pop, ratings));
+        }
+    }
+}
+
+// What a run returns
+pub struct RunResult<T> {
+    // Best individual ever seen and its rating
+    pub best: T,
+    pub rating: f32,
+    // Last generation and its ratings
+    pub population: Vec<T>,
+    pub ratings: Vec<f32>,
+    pub generations: usize,
+    pub evaluations: usize,
+    pub elapsed: Duration,
+    // Why the criterion stopped the run
+    pub reason: String,
+    // Best and mean rating of every generation, 0 being the initial one
+    pub best_history: Vec<f32>,
+    pub mean_history: Vec<f32>,
+    // Empty when the diversity was not measured
+    pub diversity_history: Vec<f32>,
+    // Left empty by runners without archives
+    pub hall_of_fame: HallOfFame<T>,
+    pub pareto: Option<ParetoArchive<T>>
+}
+
+impl<T> RunResult<T> {
+
+    pub(crate) fn new(best: T, rating: f32, population: Vec<T>, ratings: Vec<f32>, state: RunState, reason: String) -> Self {
+        Self {
+            best,
+            rating,
+            population,
+            ratings,
+            generations: state.generation,
+            evaluations: state.evaluations,
+            elapsed: state.elapsed,
+            reason,
+            best_history: state.best_history,
+            mean_history: state.mean_history,
+            diversity_history: state.diversity_history,
+            hall_of_fame: HallOfFame::new(0),
+            pareto: None
+        }
+    }
+}
+
+#[test]
+fn test_hall_of_fame() {
+    let mut hall = HallOfFame::new(3);
+    hall.update(&[1, 2, 2, 5], &[1.0, 2.0, 2.0, 5.0]);
+    hall.update(&[0, 4], &[0.0, 4.0]);
+    assert_eq!(hall.entries(), &[(5, 5.0), (4, 4.0), (2, 2.0)]);
+
+    let mut hall = HallOfFame::unique(3);
+    hall.update(&[1, 2, 2, 5], &[1.0, 2.0, 2.0, 5.0]);
+    assert_eq!(hall.entries(), &[(5, 5.0), (2, 2.0), (1, 1.0)]);
+    assert_eq!(hall.best(), Some(&(5, 5.0)));
+}
+
+#[test]
+fn test_pareto_archive() {
+    struct Split;
+
+    impl Objectives<(f64, f64)> for Split {
+
+        fn objectives(&self, pop: &(f64, f64)) -> Vec<f64> {
+            vec![pop.0, pop.1]
+        }
+    }
+
+    let mut archive = ParetoArchive::new(Split);
+    archive.update(&[(1.0, 1.0), (2.0, 0.0), (0.0, 2.0)]);
+    archive.update(&[(1.5, 1.5), (0.0, 2.0), (0.5, 0.5)]);
+
+    let mut front: Vec<(f64, f64)> = archive.entries().iter().map(|(p, _)| *p).collect();
+    front.sort_by(|a, b| a.0.total_cmp(&b.0));
+    assert_eq!(front, vec![(0.0, 2.0), (1.5, 1.5), (2.0, 0.0)]);
+}
+
+#[test]
+fn test_run_result() {
+    use crate::criterion::{Criterion, Iterations};
+    use crate::genome::bits::{BitFlip, OneMax, OnePoint, RandomBits};
+    use crate::mating::Random;
+    use crate::selector::Tournament;
+
+    let mut stop_crit = Iterations::new(20);
+    let result = crate::generate(&RandomBits{ len: 16 }, &OneMax, &Tournament{ max_pop: 20 }, &Random, &OnePoint,
+        &BitFlip{ rate: 1.0 / 16.0 }, &mut stop_crit, 50);
+
+    assert_eq!(result.generations, 20);
+    assert_eq!(result.best_history.len(), 21);
+    assert_eq!(result.evaluations, 50 * 21);
+    assert_eq!((result.population.len(), result.ratings.len()), (50, 50));
+    assert_eq!(result.rating, result.best_history.iter().copied().fold(f32::MIN, f32::max));
+    assert_eq!(result.hall_of_fame.best().map(|(_, r)| *r), Some(result.rating));
+    assert_eq!(result.reason, stop_crit.reason());
+}
+
+#[test]
+fn test_archived_run() {
+    use crate::criterion::Iterations;
+    use crate::genome::bits::{BitFlip, BitGenome, OneMax, OnePoint, RandomBits};
+    use crate::mating::Random;
+    use crate::memetic::{Inheritance, Memetic, NoLocalSearch, Target};
+    use crate::selector::Tournament;
+
+    // Ones of each half
+    struct Halves;
+
+    impl Objectives<BitGenome> for Halves {
+
+        fn objectives(&self, pop: &BitGenome) -> Vec<f64> {
+            let half = pop.len() / 2;
+            vec![pop.iter().take(half).filter(|b| *b).count() as f64, pop.iter().skip(half).filter(|b| *b).count() as f64]
+        }
+    }
+
+    let memetic = Memetic{ local_search: NoLocalSearch, probability: 0.0, budget: 0, target: Target::Offspring,
+        inheritance: Inheritance::Lamarckian };
+    let archives = Archives{ hall_of_fame: HallOfFame::unique(5), pareto: Some(ParetoArchive::new(Halves)), diversity: None };
+    let result = crate::generate_archived(&RandomBits{ len: 16 }, &OneMax, &Tournament{ max_pop: 20 }, &Random,
+        &OnePoint, &BitFlip{ rate: 1.0 / 16.0 }, &memetic, archives, &mut Iterations::new(20), 50);
+
+    let hall = result.hall_of_fame.entries();
+    assert_eq!(hall.len(), 5);
+    assert_eq!(hall[0].1, result.rating);
+    assert!(hall.iter().enumerate().all(|(i, (a, _))| hall[i + 1..].iter().all(|(b, _)| a != b)));
+
+    let front = result.pareto.as_ref().unwrap().entries();
+    assert!(!front.is_empty());
+    for (p, values) in front {
+        assert_eq!(*values, Halves.objectives(p));
+        assert!(front.iter().all(|(_, other)| !dominates(other, values)));
+    }
+    // The best individual met is on the front, or dominated by one of it
+    let best = Halves.objectives(&result.best);
+    assert!(front.iter().any(|(_, values)| *values == best || dominates(values, &best)));
+}
@@ -1,23 +1,36 @@
 use rand::{thread_rng, distributions::WeightedIndex, prelude::Distribution, Rng, seq::SliceRandom};
 
 pub trait Selector<T> {
-    fn selector(&self, pop: &Vec<T>, ratings: &Vec<f32>) -> Vec<T>;
+    // Indices of the selected individuals, so their ratings can follow them
+    fn select(&self, ratings: &[f32]) -> Vec<usize>;
+
+    // Selectors that need to look at the individuals themselves override this
+    fn select_from(&self, _pop: &[T], ratings: &[f32]) -> Vec<usize> {
+        self.select(ratings)
+    }
+
+    fn selector(&self, pop: &[T], ratings: &[f32]) -> Vec<T>
+    where
+        T: Clone {
+
+        self.select_from(pop, ratings).into_iter().map(|i| pop[i].clone()).collect()
+    }
 }
 
 pub struct Rating {
     pub max_pop: usize
 }
 
-impl<T: Clone> Selector<T> for Rating {
+impl<T> Selector<T> for Rating {
 
-    fn selector(&self, pop: &Vec<T>, ratings: &Vec<f32>) -> Vec<T> {
+    fn select(&self, ratings: &[f32]) -> Vec<usize> {
         let mut selected = Vec::with_capacity(self.max_pop);
         let mut rng = thread_rng();
 
         let dist = WeightedIndex::new(ratings).unwrap();
 
         for _ in 0..self.max_pop {
-            selected.push(pop[dist.sample(&mut rng)].clone());
+            selected.push(dist.sample(&mut rng));
         }
 
         selected
@@ -28,17 +41,17 @@ pub struct Elitism {
     pub max_pop: usize
 }
 
-impl<T: Clone> Selector<T> for Elitism {
+impl<T> Selector<T> for Elitism {
 
-    fn selector(&self, pop: &Vec<T>, ratings: &Vec<f32>) -> Vec<T> {
+    fn select(&self, ratings: &[f32]) -> Vec<usize> {
         let mut selected = Vec::with_capacity(self.max_pop);
 
-        let mut pairs: Vec<(&T, &f32)> =pop.iter().zip(ratings.iter()).collect();
-        pairs.sort_by(|a, b| (*a).1.partial_cmp((*b).1).unwrap());
+        let mut pairs: Vec<(usize, &f32)> = ratings.iter().enumerate().collect();
+        pairs.sort_by(|a, b| a.1.partial_cmp(b.1).unwrap());
         pairs.reverse();
         let mut pairs_iter = pairs.iter();
         for _ in 0..self.max_pop {
-            selected.push(pairs_iter.next().unwrap().0.clone());
+            selected.push(pairs_iter.next().unwrap().0);
         }
 
         selected
@@ -51,7 +64,7 @@ pub struct Rank {
 
 impl Rank {
 
-    fn calculate_rank(&self, ratings: &Vec<f32>) -> Vec<usize> {
+    fn calculate_rank(&self, ratings: &[f32]) -> Vec<usize> {
         let mut ranks = vec![0; ratings.len()];
 
         let mut indices: Vec<usize> = (0..ratings.len()).collect();
@@ -66,9 +79,9 @@ impl Rank {
     }
 }
 
-impl<T: Clone> Selector<T> for Rank {
+impl<T> Selector<T> for Rank {
 
-    fn selector(&self, pop: &Vec<T>, ratings: &Vec<f32>) -> Vec<T> {
+    fn select(&self, ratings: &[f32]) -> Vec<usize> {
         let mut selected = Vec::with_capacity(self.max_pop);
         let mut rng = thread_rng();
         let ranks = self.calculate_rank(ratings);
@@ -76,7 +89,7 @@ impl<T: Clone> Selector<T> for Rank {
         let dist = WeightedIndex::new(ranks).unwrap();
 
         for _ in 0..self.max_pop {
-            selected.push(pop[dist.sample(&mut rng)].clone());
+            selected.push(dist.sample(&mut rng));
         }
 
         selected
@@ -87,9 +100,9 @@ pub struct Tournament {
     pub max_pop: usize,
 }
 
-impl<T: Clone> Selector<T> for Tournament {
+impl<T> Selector<T> for Tournament {
 
-    fn selector(&self, pop: &Vec<T>, ratings: &Vec<f32>) -> Vec<T> {
+    fn select(&self, ratings: &[f32]) -> Vec<usize> {
         let mut selected = Vec::with_capacity(self.max_pop);
         let mut participants = Vec::with_capacity(self.max_pop);
         let mut rng = thread_rng();
@@ -98,9 +111,9 @@ impl<T: Clone> Selector<T> for Tournament {
 
             for _ in 0..self.max_pop {
                 
-                let mut index = rng.gen_range(0..pop.len());
+                let mut index = rng.gen_range(0..ratings.len());
                 while participants.contains(&index) {
-                    index = rng.gen_range(0..pop.len());
+                    index = rng.gen_range(0..ratings.len());
                 }
                 participants.push(index);
             }
@@ -115,7 +128,7 @@ impl<T: Clone> Selector<T> for Tournament {
                 }
             }
 
-            selected.push(pop[best].clone());
+            selected.push(best);
 
             participants.clear();
 
@@ -130,22 +143,23 @@ pub struct BestAndRand {
     pub rand_pop: usize
 }
 
-impl<T: Clone> Selector<T> for BestAndRand {
+impl<T> Selector<T> for BestAndRand {
 
-    fn selector(&self, pop: &Vec<T>, ratings: &Vec<f32>) -> Vec<T> {
+    fn select(&self, ratings: &[f32]) -> Vec<usize> {
         let mut selected = Vec::with_capacity(self.best_pop + self.rand_pop);
 
         let mut rng = thread_rng();
 
-        let mut pairs: Vec<(&T, &f32)> =pop.iter().zip(ratings.iter()).collect();
-        pairs.sort_by(|a, b| (*a).1.partial_cmp((*b).1).unwrap());
+        let mut pairs: Vec<(usize, &f32)> = ratings.iter().enumerate().collect();
+        pairs.sort_by(|a, b| a.1.partial_cmp(b.1).unwrap());
         pairs.reverse();
         let mut pairs_iter = pairs.iter();
         for _ in 0..self.best_pop {
-            selected.push(pairs_iter.next().unwrap().0.clone());
+            selected.push(pairs_iter.next().unwrap().0);
         }
 
-        selected.extend(pop.choose_multiple(&mut rng, self.rand_pop).cloned());
+        let indices: Vec<usize> = (0..ratings.len()).collect();
+        selected.extend(indices.choose_multiple(&mut rng, self.rand_pop));
 
         selected
     }
@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+use rand::{thread_rng, Rng};
+
+use crate::mating::Distance;
+
+// Bookkeeping of a run, handed to the criteria after each generation so they
+// don't have to track it themselves
+pub struct RunState {
+    // 0 is the initial population
+    pub generation: usize,
+    pub elapsed: Duration,
+    pub evaluations: usize,
+    pub ratings: Vec<f32>,
+    // Best and mean rating of every generation so far
+    pub best_history: Vec<f32>,
+    pub mean_history: Vec<f32>,
+    // Standard deviation of the current ratings, their spread and not the one
+    // of the genomes
+    pub rating_std_dev: f32,
+    // Genotype diversity of every generation so far, empty unless the runner
+    // was given a `Diversity` to measure it
+    pub diversity_history: Vec<f32>,
+    // Filled by adaptive operators, one entry per operator they choose from
+    pub crossover_stats: Vec<OperatorStats>,
+    pub mutation_stats: Vec<OperatorStats>,
+    start: Instant
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OperatorStats {
+    pub name: String,
+    // Applications that were credited, and those improving on the parents
+    pub uses: usize,
+    pub successes: usize,
+    // Current chance of being picked
+    pub probability: f64,
+    // Estimated reward
+    pub quality: f64
+}
+
+impl OperatorStats {
+
+    pub fn success_rate(&self) -> f64 {
+        self.successes as f64 / self.uses.max(1) as f64
+    }
+}
+
+impl Default for RunState {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunState {
+
+    pub fn new() -> Self {
+        Self {
+            generation: 0,
+            elapsed: Duration::ZERO,
+            evaluations: 0,
+            ratings: vec![],
+            best_history: vec![],
+            mean_history: vec![],
+            rating_std_dev: 0.0,
+            diversity_history: vec![],
+            crossover_stats: vec![],
+            mutation_stats: vec![],
+            start: Instant::now()
+        }
+    }
+
+    // Called once per generation with its ratings and the number of calls
+    // made to the evaluator to get them
+    pub fn record(&mut self, ratings: &[f32], evaluations: usize) {
+        if !self.best_history.is_empty() {
+            self.generation += 1;
+        }
+        self.elapsed = self.start.elapsed();
+        self.evaluations += evaluations;
+
+        self.ratings.clear();
+        self.ratings.extend_from_slice(ratings);
+
+        let len = ratings.len().max(1) as f32;
+        let mean = ratings.iter().sum::<f32>() / len;
+        let variance = ratings.iter().map(|r| (r - mean).powi(2)).sum::<f32>() / len;
+
+        self.best_history.push(ratings.iter().copied().fold(f32::MIN, f32::max));
+        self.mean_history.push(mean);
+        self.rating_std_dev = variance.sqrt();
+    }
+
+    // Best rating of the current generation
+    pub fn best(&self) -> f32 {
+        self.best_history.last().copied().unwrap_or(f32::MIN)
+    }
+
+    pub fn best_ever(&self) -> f32 {
+        self.best_history.iter().copied().fold(f32::MIN, f32::max)
+    }
+
+    // Genotype diversity of the current generation, if measured
+    pub fn diversity(&self) -> Option<f32> {
+        self.diversity_history.last().copied()
+    }
+}
+
+// Genotype diversity of a population, higher when the genomes are further
+// apart
+pub trait Diversity<T> {
+    fn diversity(&self, pop: &[T], ratings: &[f32]) -> f32;
+}
+
+// Mean `distance` between individuals, over every pair when there are at most
+// `pairs` of them and over `pairs` random ones otherwise
+pub struct MeanDistance<D> {
+    pub distance: D,
+    pub pairs: usize
+}
+
+impl<T, D: Distance<T>> Diversity<T> for MeanDistance<D> {
+
+    fn diversity(&self, pop: &[T], ratings: &[f32]) -> f32 {
+        let n = pop.len();
+        if n < 2 {
+            return 0.0;
+        }
+
+        let measure = |i: usize, j: usize| self.distance.distance(&pop[i], ratings[i], &pop[j], ratings[j]);
+        if n * (n - 1) / 2 <= self.pairs {
+            let total: f32 = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j))).map(|(i, j)| measure(i, j)).sum();
+            return total / (n * (n - 1) / 2) as f32;
+        }
+
+        let mut rng = thread_rng();
+        let total: f32 = (0..self.pairs).map(|_| {
+            let i = rng.gen_range(0..n);
+            let j = (i + rng.gen_range(1..n)) % n;
+            measure(i, j)
+        }).sum();
+        total / self.pairs.max(1) as f32
+    }
+}
+
+#[test]
+fn test_record() {
+    let mut state = RunState::new();
+
+    state.record(&[1.0, 3.0], 2);
+    state.record(&[2.0, 2.0], 2);
+
+    assert_eq!(state.generation, 1);
+    assert_eq!(state.evaluations, 4);
+    assert_eq!(state.best_history, vec![3.0, 2.0]);
+    assert_eq!(state.best_ever(), 3.0);
+    assert_eq!(state.rating_std_dev, 0.0);
+}
+
+#[test]
+fn test_mean_distance() {
+    use crate::mating::HammingDistance;
+
+    let pop = vec![String::from("aa"), String::from("ab"), String::from("bb")];
+    let diversity = MeanDistance{ distance: HammingDistance, pairs: 10 };
+    assert_eq!(diversity.diversity(&pop, &[0.0; 3]), 4.0 / 3.0);
+
+    let sampled = MeanDistance{ distance: HammingDistance, pairs: 2 }.diversity(&pop, &[0.0; 3]);
+    assert!((1.0..=2.0).contains(&sampled));
+    assert_eq!(diversity.diversity(&pop[..1], &[0.0]), 0.0);
+}
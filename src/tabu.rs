@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+
+use crate::criterion::Criterion;
+use crate::evaluator::Evaluator;
+use crate::generator::Generator;
+use crate::mutation::Mutation;
+use crate::result::RunResult;
+use crate::state::RunState;
+use crate::{best_index, fill_ratings};
+
+// Tabu search from a generated individual. Each step samples `candidates`
+// neighbours with `mutation` and moves to the best one that was not visited
+// in the last `tenure` steps, even if it is worse. Tabu neighbours are still
+// taken when better than the best ever (aspiration). Each step is a
+// generation, rated with the candidates, for the criterion. Returns the best
+// individual met.
+pub fn tabu_search<T, G, E, M, F>(generator: &G, evaluator: &E, mutation: &M, stop_crit: &mut F,
+    tenure: usize, candidates: u32) -> RunResult<T>
+where
+    G: Generator<T>,
+    E: Evaluator<T> + Send + Sync,
+    M: Mutation<T>,
+    F: Criterion,
+    T: Clone + PartialEq + Send + Sync {
+
+    let mut state = RunState::new();
+
+    let mut current = generator.generator();
+    let mut current_rating = evaluator.evaluator(&current);
+    let (mut best, mut best_rating) = (current.clone(), current_rating);
+
+    let mut tabu = VecDeque::with_capacity(tenure + 1);
+    tabu.push_back(current.clone());
+
+    state.record(&[current_rating], 1);
+
+    let mut ratings = Vec::with_capacity(candidates as usize);
+    while !stop_crit.criterion(&state) {
+        let neighbours: Vec<T> = (0..candidates).map(|_| {
+            let mut neighbour = current.clone();
+            mutation.mutation(&mut neighbour);
+            neighbour
+        }).collect();
+
+        ratings.clear();
+        fill_ratings(candidates, &neighbours, evaluator, &mut ratings);
+        let improvements: Vec<f32> = ratings.iter().map(|r| r - current_rating).collect();
+        mutation.credit(&improvements);
+        state.mutation_stats = mutation.stats();
+        state.record(&ratings, candidates as usize);
+
+        let allowed: Vec<f32> = neighbours.iter().zip(&ratings)
+            .map(|(n, r)| if *r > best_rating || !tabu.contains(n) { *r } else { f32::MIN })
+            .collect();
+        let (index, rating) = best_index(&allowed);
+        // Every neighbour is tabu
+        if rating == f32::MIN {
+            continue;
+        }
+
+        current.clone_from(&neighbours[index]);
+        current_rating = rating;
+        if rating > best_rating {
+            best.clone_from(&current);
+            best_rating = rating;
+        }
+        tabu.push_back(current.clone());
+        if tabu.len() > tenure {
+            tabu.pop_front();
+        }
+    }
+
+    RunResult::new(best, best_rating, vec![current], vec![current_rating], state, stop_crit.reason())
+}
+
+// Number of elements at their place
+#[cfg(test)]
+struct Sorted;
+
+#[cfg(test)]
+impl Evaluator<crate::genome::permutation::Permutation> for Sorted {
+
+    fn evaluator(&self, pop: &crate::genome::permutation::Permutation) -> f32 {
+        pop.as_slice().iter().enumerate().filter(|(i, x)| *i == **x).count() as f32
+    }
+}
+
+#[test]
+fn test_tabu_search() {
+    use crate::criterion::{Iterations, Mark};
+    use crate::genome::permutation::{Permutation, RandomPermutation, Swap};
+
+    let mut stop_crit = Mark{ max_rating: 10.0 }.or(Iterations::new(2000));
+    let result = tabu_search(&RandomPermutation{ len: 10 }, &Sorted, &Swap, &mut stop_crit, 20, 16);
+
+    assert_eq!(result.rating, 10.0);
+    assert_eq!(result.best, Permutation::identity(10));
+}
+
+#[test]
+fn test_tabu_search_credits_mutation() {
+    use crate::adaptive::{AdaptiveMutation, Strategy};
+    use crate::criterion::Iterations;
+    use crate::genome::permutation::{Insertion, RandomPermutation, Swap};
+
+    let mutation = AdaptiveMutation::new(vec![Box::new(Swap), Box::new(Insertion)], Strategy::Ucb{ c: 0.5 });
+    let mut stop_crit = Iterations::new(10);
+    tabu_search(&RandomPermutation{ len: 10 }, &Sorted, &mutation, &mut stop_crit, 20, 16);
+
+    // Every neighbour is credited
+    let uses: usize = mutation.stats().iter().map(|s| s.uses).sum();
+    assert_eq!(uses, 10 * 16);
+}